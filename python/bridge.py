@@ -42,13 +42,14 @@ def new_simulation(simulation, serialized_setup):
     )
 
 
-def load_simulation(simulation):
+def load_simulation(simulation, force_unlock=False):
     drop_context(simulation)
 
     blended_mpm_context_dict[simulation.uuid] = blended_mpm_wrap.load(
         simulation.uuid,
         simulation.cache_directory,
         giga_f32_to_u64(simulation.max_giga_bytes_on_disk),
+        force_unlock,
     )
 
 
@@ -72,6 +73,15 @@ def computing(simulation):
     )
 
 
+def frame_policy_as_json(simulation):
+    return json.dumps(
+        {
+            "checkpoint_interval": simulation.checkpoint_interval,
+            "lean_attributes": sorted(simulation.lean_attributes),
+        }
+    )
+
+
 def start_compute_initial_frame(simulation):
     blended_mpm_context_dict[simulation.uuid].start_compute(
         simulation.time_step,
@@ -80,6 +90,13 @@ def start_compute_initial_frame(simulation):
         0,
         1,
         giga_f32_to_u64(simulation.max_giga_bytes_on_disk),
+        giga_f32_to_u64(simulation.max_giga_bytes_in_memory),
+        simulation.number_of_threads,
+        simulation.low_priority,
+        simulation.store_queue_depth,
+        json.dumps(simulation.frame_codec),
+        frame_policy_as_json(simulation),
+        json.dumps(simulation.eviction_policy),
     )
 
 
@@ -91,6 +108,13 @@ def start_compute(simulation, from_frame):
         from_frame,
         simulation.bake_frames,
         giga_f32_to_u64(simulation.max_giga_bytes_on_disk),
+        giga_f32_to_u64(simulation.max_giga_bytes_in_memory),
+        simulation.number_of_threads,
+        simulation.low_priority,
+        simulation.store_queue_depth,
+        json.dumps(simulation.frame_codec),
+        frame_policy_as_json(simulation),
+        json.dumps(simulation.eviction_policy),
     )
 
 
@@ -104,6 +128,12 @@ def available_frames(simulation):
     return blended_mpm_context_dict[simulation.uuid].available_frames()
 
 
+def manifest(simulation):
+    if not context_exists(simulation):
+        return []
+    return json.loads(blended_mpm_context_dict[simulation.uuid].manifest())
+
+
 def available_attributes(simulation, frame):
     return blended_mpm_context_dict[simulation.uuid].available_attributes(frame)
 
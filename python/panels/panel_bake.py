@@ -181,6 +181,9 @@ class OBJECT_PT_Blended_MPM_Bake(bpy.types.Panel):
             # col.prop(simulation, "explicit")
             # col.prop(simulation, "debug_mode")
             col.prop(simulation, "bake_frames")
+            col.prop(simulation, "number_of_threads")
+            col.prop(simulation, "low_priority")
+            col.prop(simulation, "store_queue_depth")
 
             row = self.layout.row()
             row.operator("object.blended_mpm_bake_start_from_latest", icon="PHYSICS")
@@ -64,6 +64,9 @@ def draw_object_settings(layout, settings):
             layout.prop(settings, "viscosity")
             layout.prop(settings, "dilation")
             layout.prop(settings, "randomness")
+            layout.prop(settings, "drag")
+            layout.prop(settings, "stiffness_damping")
+            layout.prop(settings, "separate_velocity_field")
             layout.prop(settings, "initial_linear_velocity")
             layout.prop(settings, "initial_angular_velocity")
         case e if e == OBJECT_ENUM_FLUID:
@@ -73,11 +76,17 @@ def draw_object_settings(layout, settings):
             layout.prop(settings, "viscosity")
             layout.prop(settings, "dilation")
             layout.prop(settings, "randomness")
+            layout.prop(settings, "drag")
+            layout.prop(settings, "stiffness_damping")
+            layout.prop(settings, "separate_velocity_field")
             layout.prop(settings, "initial_linear_velocity")
             layout.prop(settings, "initial_angular_velocity")
         case e if e == OBJECT_ENUM_COLLIDER:
             layout.prop(settings, "sticky_factor")
             layout.prop(settings, "friction_factor")
+            layout.prop(settings, "static_friction_factor")
+            layout.prop(settings, "restitution")
+            layout.prop(settings, "adhesion_velocity")
 
 
 def selection_eligible_for_input(context):
@@ -250,6 +259,9 @@ Note that this also discards all computed frames in the cache."""
             self.layout.label(
                 text=f"The previous cache will be overwritten: {available_frames(simulation)} frames"
             )
+            self.layout.label(
+                text="If only collider animations changed, the frames before are kept."
+            )
         tutorial_msg(
             self.layout,
             context,
@@ -319,6 +331,10 @@ class OBJECT_PT_Blended_MPM_Input(bpy.types.Panel):
             particle_size.prop(simulation.to_cache, "particle_size")
             to_cache.prop(simulation.to_cache, "frames_per_second")
             to_cache.prop(simulation.to_cache, "gravity")
+            to_cache.prop(simulation.to_cache, "contact_friction_factor")
+            to_cache.prop(simulation.to_cache, "air_drag")
+            to_cache.prop(simulation.to_cache, "seed")
+            to_cache.prop(simulation.to_cache, "deterministic")
             to_cache.prop(simulation.to_cache, "simulation_scale")
 
             if context_exists(simulation):
@@ -17,9 +17,7 @@
 # along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
 import json
-import os
 import uuid
-from pathlib import Path
 
 import bpy
 
@@ -156,25 +154,25 @@ please use your OS's file browser."""
         return {"FINISHED"}
 
 
-class OBJECT_OT_Blended_MPM_Remove_Lock_File(bpy.types.Operator):
-    bl_idname = "object.blended_mpm_remove_lock_file"
-    bl_label = "Remove Lock"
+class OBJECT_OT_Blended_MPM_Force_Unlock(bpy.types.Operator):
+    bl_idname = "object.blended_mpm_force_unlock"
+    bl_label = "Take Over Lock"
     bl_description = """Use with care!
 
 If the lock file is present, it usually means that another simulation is using this cache.
-However, the lock file can remain after a crash, in which case it must be deleted."""
+Locks of crashed Blender instances on this computer are taken over when reloading,
+but a crash on another computer sharing the cache must be resolved by hand."""
     bl_options = {"REGISTER"}
 
     uuid: bpy.props.StringProperty()  # type: ignore
 
-    def execute(self, _context):
+    def execute(self, context):
         simulation = get_simulation_by_uuid(self.uuid)
-        lock_file = Path(simulation.cache_directory) / "lock"
-        if os.path.exists(lock_file):
-            os.remove(lock_file)
-            self.report({"INFO"}, "Removed lock file.")
-        else:
-            self.report({"INFO"}, "No lock file present.")
+        simulation.last_exception = ""
+        simulation.loaded_frame = -1
+        load_simulation(simulation, force_unlock=True)
+        sync_simulation(simulation, context.scene.frame_current)
+        self.report({"INFO"}, "Took over the lock and reloaded simulation.")
         return {"FINISHED"}
 
 
@@ -254,13 +252,21 @@ class OBJECT_PT_Blended_MPM_Overview(bpy.types.Panel):
                 col = body.column()
                 col.enabled = not computing(simulation)
                 col.prop(simulation, "max_giga_bytes_on_disk")
+                col.prop(simulation, "eviction_policy")
+                col.prop(simulation, "max_giga_bytes_in_memory")
+                col.prop(simulation, "frame_codec")
+                col.prop(simulation, "checkpoint_interval")
+                col.prop(simulation, "lean_attributes")
 
                 row = body.row()
                 if not context_exists(simulation) and simulation_cache_locked(
                     simulation
                 ):
                     row.operator(
-                        "object.blended_mpm_remove_lock_file", icon="WARNING_LARGE"
+                        "object.blended_mpm_reload", icon="FILE_CACHE"
+                    ).uuid = simulation.uuid
+                    row.operator(
+                        "object.blended_mpm_force_unlock", icon="WARNING_LARGE"
                     ).uuid = simulation.uuid
                 elif simulation_cache_exists(simulation):
                     tut = row.column()
@@ -292,7 +298,7 @@ classes = [
     OBJECT_OT_Blended_MPM_Add_Simulation,
     OBJECT_OT_Blended_MPM_Reload,
     OBJECT_OT_Blended_MPM_Remove_Simulation,
-    OBJECT_OT_Blended_MPM_Remove_Lock_File,
+    OBJECT_OT_Blended_MPM_Force_Unlock,
     OBJECT_OT_Blended_MPM_Show_Message,
     OBJECT_PT_Blended_MPM_Overview,
 ]
@@ -150,6 +150,37 @@ more samples under compression and less under streching.""",
         options=set(),
     )  # type: ignore
 
+    drag: bpy.props.FloatProperty(
+        name="Drag",
+        description="""How quickly the object's motion dies down. Unit: 1 / s.
+Damps the velocity proportional to the mass, 0 doesn't damp at all.""",
+        default=0.0,
+        min=0.0,
+        max=1000.0,
+        precision=3,
+        options=set(),
+    )  # type: ignore
+
+    stiffness_damping: bpy.props.FloatProperty(
+        name="Stiffness Damping",
+        description="""How quickly the object's wobbling dies down. Unit: s.
+Damps the deformation rate proportional to the stiffness, 0 doesn't damp at all.""",
+        default=0.0,
+        min=0.0,
+        max=10.0,
+        precision=4,
+        options=set(),
+    )  # type: ignore
+
+    separate_velocity_field: bpy.props.BoolProperty(
+        name="Separate Velocity Field",
+        description="""Don't merge with other objects on contact.
+Instead, the objects can collide, slide along and separate again.
+This costs some performance for each object it's enabled on.""",
+        default=False,
+        options=set(),
+    )  # type: ignore
+
     sticky_factor: bpy.props.FloatProperty(
         name="Sticky Factor",
         description="""How sticky the collider object should be. Unit: None.
@@ -172,6 +203,40 @@ more samples under compression and less under streching.""",
         options=set(),
     )  # type: ignore
 
+    static_friction_factor: bpy.props.FloatProperty(
+        name="Static Friction Factor",
+        description="""How much the rigid object resists starting to slide. Unit: None.
+Sliding only starts once the tangential motion overcomes this,
+0 means there is no extra resistance beyond the friction factor.""",
+        default=0.0,
+        min=0.0,
+        max=10.0,
+        precision=1,
+        options=set(),
+    )  # type: ignore
+
+    restitution: bpy.props.FloatProperty(
+        name="Restitution",
+        description="""How bouncy the collider object should be. Unit: None.
+1 reflects all of the impact velocity, 0 doesn't bounce at all.""",
+        default=0.0,
+        min=0.0,
+        max=1.0,
+        precision=2,
+        options=set(),
+    )  # type: ignore
+
+    adhesion_velocity: bpy.props.FloatProperty(
+        name="Adhesion Velocity",
+        description="""How fast material has to move away to leave the collider object. Unit: m / s.
+0 means the material can always leave freely.""",
+        default=0.0,
+        min=0.0,
+        max=100.0,
+        precision=2,
+        options=set(),
+    )  # type: ignore
+
     object_enum: bpy.props.EnumProperty(
         items=[
             (
@@ -136,6 +136,21 @@ Changes have *no effect* on *already running* bakes.""",
         precision=2,
         options=set(),
     )  # type: ignore
+    max_giga_bytes_in_memory: bpy.props.FloatProperty(
+        name="Max Memory (Gigabytes)",
+        description="""Simulations can use a lot of memory,
+for example with a grid node size that is too small for the scene.
+
+Once it is exceeded, the computation will stop
+instead of the system running out of memory.
+Frames waiting to be written to disk are included.
+
+(Re)Start baking to manifest changes.""",
+        default=16.0,
+        min=0.0,
+        precision=2,
+        options=set(),
+    )  # type: ignore
 
     # ----------------------------------------------------------------
     # from_cache is read-only but can be overwritten with to_cache
@@ -214,6 +229,112 @@ Smaller grid node size, stiffer objects and higher velocities dictate a smaller
         default=False,
         options=set(),
     )  # type: ignore
+    number_of_threads: bpy.props.IntProperty(
+        name="Threads",
+        description="""The number of threads used for baking, 0 uses all cores.
+Limit it to leave room for other simulations or applications.
+
+(Re)Start baking to manifest changes.""",
+        default=0,
+        min=0,
+        options=set(),
+    )  # type: ignore
+    low_priority: bpy.props.BoolProperty(
+        name="Low Priority",
+        description="""Bake with a lower thread priority s.t. Blender stays responsive.
+Only has an effect on Linux and Windows.
+
+(Re)Start baking to manifest changes.""",
+        default=True,
+        options=set(),
+    )  # type: ignore
+    store_queue_depth: bpy.props.IntProperty(
+        name="Store Queue Depth",
+        description="""The number of frames kept in memory while waiting to be written to disk.
+Baking pauses when they're all waiting, a deeper queue smooths out slow disks at the cost of memory.
+
+(Re)Start baking to manifest changes.""",
+        default=2,
+        min=1,
+        options=set(),
+    )  # type: ignore
+    frame_codec: bpy.props.EnumProperty(
+        items=[
+            ("None", "None", "Store frames uncompressed."),
+            ("Lz4", "LZ4", "Fast compression that keeps up with baking."),
+            ("Zstd", "Zstandard", "Smaller frames, but slower to store and load."),
+        ],
+        name="Frame Compression",
+        description="""How baked frames are compressed on disk.
+Frames already baked keep their compression.
+
+(Re)Start baking to manifest changes.""",
+        default="Lz4",
+        options=set(),
+    )  # type: ignore
+    checkpoint_interval: bpy.props.IntProperty(
+        name="Checkpoint Interval",
+        description="""Every this many frames the complete simulation state is stored.
+The frames in between only keep the particle positions and the lean attributes.
+Baking continues from the last checkpoint, 1 makes every frame a checkpoint.
+
+(Re)Start baking to manifest changes.""",
+        default=10,
+        min=1,
+        options=set(),
+    )  # type: ignore
+    lean_attributes: bpy.props.EnumProperty(
+        items=[
+            ("Masses", "Masses", "Particle masses and initial volumes."),
+            ("Velocities", "Velocities", "Particle velocities."),
+            (
+                "PositionGradients",
+                "Transformations",
+                "Particle deformations, needed for the transformations.",
+            ),
+            (
+                "ElasticEnergies",
+                "Energies and Pressures",
+                "Elastic energies of solids and pressures of fluids.",
+            ),
+            (
+                "ColliderInsides",
+                "Collider Insides",
+                "Which side of colliders particles are.",
+            ),
+            ("Grids", "Grids", "The grid momentums and collider distances."),
+        ],
+        name="Lean Attributes",
+        description="""What frames between checkpoints keep besides the particle positions.
+Attributes that aren't kept can't be output for these frames.
+
+(Re)Start baking to manifest changes.""",
+        default={"Velocities", "PositionGradients"},
+        options={"ENUM_FLAG"},
+    )  # type: ignore
+    eviction_policy: bpy.props.EnumProperty(
+        items=[
+            ("Fail", "Stop Baking", "Stop baking at the disk budget."),
+            (
+                "ThinCheckpoints",
+                "Thin Out Checkpoints",
+                "Turn older checkpoints into lean frames.",
+            ),
+            ("DropGrids", "Drop Grids", "Remove the grids from older frames."),
+            (
+                "RollingWindow",
+                "Rolling Window",
+                "Remove older frames completely, only the latest ones are kept.",
+            ),
+        ],
+        name="At Disk Budget",
+        description="""What to give up once the disk budget is reached, oldest frames first.
+The last checkpoint is always kept to continue baking from.
+
+(Re)Start baking to manifest changes.""",
+        default="ThinCheckpoints",
+        options=set(),
+    )  # type: ignore
     loaded_frame: bpy.props.IntProperty(
         name="Loaded Simulation Frame",
         description="""The index of the currently displayed simulation frame.
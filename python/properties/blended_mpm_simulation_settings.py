@@ -82,6 +82,41 @@ Overwrite the cache to manifest changes.""",
         default=(0.0, 0.0, -9.8),
         options=set(),
     )  # type: ignore
+    contact_friction_factor: bpy.props.FloatProperty(
+        name="Contact Friction Factor",
+        description="""How much objects with separate velocity fields resist sliding along each other.
+1 is quite resistive, 0 is slippery.""",
+        default=0.3,
+        min=0.0,
+        max=10.0,
+        precision=1,
+        options=set(),
+    )  # type: ignore
+    air_drag: bpy.props.FloatProperty(
+        name="Air Drag",
+        description="""Damps all motion in the simulation. Unit: 1 / s.
+0 doesn't damp at all.""",
+        default=0.0,
+        min=0.0,
+        max=1000.0,
+        precision=3,
+        options=set(),
+    )  # type: ignore
+    seed: bpy.props.IntProperty(
+        name="Seed",
+        description="""Controls the random jitter of the particles.
+Changing it gives a different but equivalent sampling.""",
+        default=0,
+        min=0,
+        options=set(),
+    )  # type: ignore
+    deterministic: bpy.props.BoolProperty(
+        name="Deterministic",
+        description="""Identical setups give identical results.
+This comes at a small performance cost.""",
+        default=False,
+        options=set(),
+    )  # type: ignore
     simulation_scale: bpy.props.FloatProperty(
         name="Simulation Scale",
         description="""Use this to simulate things as if they were bigger or smaller.
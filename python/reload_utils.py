@@ -16,8 +16,6 @@
 # You should have received a copy of the GNU General Public License
 # along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
-import os
-from pathlib import Path
 import bpy
 
 from .bridge import load_simulation
@@ -34,14 +32,10 @@ This is useful when reloading a Blender filer with multiple simulations."""
 
     def execute(self, context):
         for simulation in context.scene.blended_mpm_scene.simulations:
-            lock_file = Path(simulation.cache_directory) / "lock"
-            if os.path.exists(lock_file):
-                os.remove(lock_file)
-                self.report({"INFO"}, "Removed lock file.")
-
             simulation.last_exception = ""
             simulation.loaded_frame = -1
-            load_simulation(simulation)
+            # confirmed in the dialog when any cache is locked
+            load_simulation(simulation, force_unlock=True)
             sync_simulation(simulation, context.scene.frame_current)
             self.report({"INFO"}, "Reloaded simulation.")
 
@@ -65,8 +59,8 @@ This is useful when reloading a Blender filer with multiple simulations."""
             for simulation in context.scene.blended_mpm_scene.simulations
             if simulation_cache_locked(simulation)
         ]:
-            self.layout.lablel(text=f"{name}")
-        self.layout.lablel(text="Confirm to remove them.")
+            self.layout.label(text=f"{name}")
+        self.layout.label(text="Confirm to take over their locks.")
 
 
 classes = [
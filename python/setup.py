@@ -100,6 +100,9 @@ def create_setup_json(simulation):
                         "viscosity": obj_settings.viscosity * simulation_scale,
                         "dilation": obj_settings.dilation,
                         "randomness": obj_settings.randomness,
+                        "drag": obj_settings.drag,
+                        "stiffness_damping": obj_settings.stiffness_damping,
+                        "separate_velocity_field": obj_settings.separate_velocity_field,
                     }
                 }
             case e if e == OBJECT_ENUM_FLUID:
@@ -111,6 +114,9 @@ def create_setup_json(simulation):
                         "viscosity": obj_settings.viscosity * simulation_scale,
                         "dilation": obj_settings.dilation,
                         "randomness": obj_settings.randomness,
+                        "drag": obj_settings.drag,
+                        "stiffness_damping": obj_settings.stiffness_damping,
+                        "separate_velocity_field": obj_settings.separate_velocity_field,
                     }
                 }
             case e if e == OBJECT_ENUM_COLLIDER:
@@ -118,6 +124,10 @@ def create_setup_json(simulation):
                     OBJECT_ENUM_COLLIDER: {
                         "sticky_factor": obj_settings.sticky_factor,
                         "friction_factor": obj_settings.friction_factor,
+                        "static_friction_factor": obj_settings.static_friction_factor,
+                        "restitution": obj_settings.restitution,
+                        "adhesion_velocity": obj_settings.adhesion_velocity
+                        * simulation_scale,
                     }
                 }
         vertices = name + "_vertices"
@@ -232,6 +242,10 @@ def create_setup_json(simulation):
         "particle_size": simulation.to_cache.particle_size,
         "frames_per_second": simulation.to_cache.frames_per_second,
         "gravity": gravity,
+        "contact_friction_factor": simulation.to_cache.contact_friction_factor,
+        "air_drag": simulation.to_cache.air_drag,
+        "seed": simulation.to_cache.seed,
+        "deterministic": simulation.to_cache.deterministic,
     }
 
     bulk_data = {
@@ -17,6 +17,13 @@ pub type T = f64;
 #[cfg(not(feature = "f64"))]
 pub type T = f32;
 
+// Sums over many particles (grid mass and momentum, energies) lose the most precision.
+// With mixed precision they're accumulated in f64 while the particles are stored in f32.
+#[cfg(any(feature = "f64", feature = "mixed_precision"))]
+pub type A = f64;
+#[cfg(not(any(feature = "f64", feature = "mixed_precision")))]
+pub type A = f32;
+
 pub trait Context: Send + Sync {
     fn new_simulation(
         &mut self,
@@ -30,6 +37,7 @@ pub trait Context: Send + Sync {
         uuid: String,
         cache_dir: PathBuf,
         max_bytes_on_disk: u64,
+        force_unlock: bool,
     ) -> Result<()>;
 
     fn get_simulation(&self, uuid: &str) -> Result<&dyn Simulation>;
@@ -42,6 +50,7 @@ pub trait Simulation {
 
     fn poll(&mut self) -> Result<Option<Task>>;
 
+    #[allow(clippy::too_many_arguments)]
     fn start_compute(
         &mut self,
         time_step: T,
@@ -50,14 +59,47 @@ pub trait Simulation {
         next_frame: usize,
         number_of_frames: usize,
         max_bytes_on_disk: u64,
+        // the states in memory, including the ones waiting to be stored
+        max_bytes_in_memory: u64,
+        // 0 uses all cores
+        number_of_threads: usize,
+        low_priority: bool,
+        // frames waiting to be stored before computing blocks
+        store_queue_depth: usize,
+        // how frames are compressed on disk
+        frame_codec: Value,
+        // which frames are checkpoints and what the others keep
+        frame_policy: Value,
+        // what to give up at the disk budget instead of failing
+        eviction_policy: Value,
     ) -> Result<()>;
     fn pause_compute(&mut self);
 
     fn available_frames(&self) -> usize;
+    fn manifest(&self) -> Vec<FrameMetadata>;
     fn available_attributes(&self, frame: usize) -> Result<Vec<Value>>;
     fn fetch_flat_attribute(&self, frame: usize, attribute: Value) -> Result<Vec<T>>;
 }
 
+// Recorded for every stored frame in the cache's manifest.
+// Frames stored before the manifest existed only know their time and bytes.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct FrameMetadata {
+    // simulated seconds
+    pub time: f64,
+    pub substeps: usize,
+    pub min_time_step: f64,
+    pub max_time_step: f64,
+    pub particles: usize,
+    // wall-clock seconds since the previous frame was computed
+    pub compute_seconds: f64,
+    // on disk
+    pub bytes: u64,
+    // only the header is left to stay within the disk budget
+    #[serde(default)]
+    pub evicted: bool,
+}
+
 #[derive(Serialize, Deserialize)]
 pub struct Task {
     pub name: String,
@@ -7,8 +7,11 @@
 // https://opensource.org/licenses/MIT.
 
 use anyhow::{ensure, Result};
-use blended_mpm_core::{Cache, Phase, PhaseInput, Report, ReportInfo, State};
+use blended_mpm_core::{
+    Cache, FrameContents, FrameStatistics, Phase, PhaseInput, Report, ReportInfo, State,
+};
 use std::{
+    mem::replace,
     num::NonZero,
     path::PathBuf,
     sync::{
@@ -48,6 +51,14 @@ struct Cli {
 
     #[arg(long)]
     no_output: bool,
+
+    /// Check the frames against their checksums and exit
+    #[arg(long)]
+    verify: bool,
+
+    /// Like verify, but truncate the cache at the first bad frame
+    #[arg(long)]
+    repair: bool,
 }
 
 fn main() -> Result<()> {
@@ -61,6 +72,8 @@ fn main() -> Result<()> {
         number_of_sub_frames,
         number_of_frames,
         no_output,
+        verify,
+        repair,
     } = Cli::parse();
 
     let output_profile = || -> Result<()> {
@@ -82,7 +95,13 @@ fn main() -> Result<()> {
         })?;
     }
 
-    let cache = Cache::load(Uuid::new_v4().to_string(), cache_dir, 10_000_000_000)?;
+    let cache = Cache::load(Uuid::new_v4().to_string(), cache_dir, 10_000_000_000, false)?;
+    if verify || repair {
+        if let Some(frame) = cache.verify(repair)? {
+            info!("first bad frame: {frame}");
+        }
+        return Ok(());
+    }
     let seconds_per_frame = 1. / cache.setup.settings.frames_per_second as f64;
 
     ensure!(
@@ -101,19 +120,21 @@ fn main() -> Result<()> {
     }
 
     let stamp = Instant::now();
+    let report = Report::new(ReportInfo {
+        name: "".to_string(),
+        completed_steps: 0,
+        steps_to_completion: NonZero::new(1).unwrap(),
+    });
 
     let mut current_state = if next_frame == 0 {
+        let statistics = FrameStatistics::start();
         let state = State::new(
             Arc::new(AtomicBool::new(true)),
-            Report::new(ReportInfo {
-                name: "".to_string(),
-                completed_steps: 0,
-                steps_to_completion: NonZero::new(1).unwrap(),
-            }),
+            report.clone(),
             &cache.setup,
         )?;
         if !no_output {
-            cache.store_frame(state.clone())?;
+            cache.store_frame(&state, FrameContents::Checkpoint, statistics, &report)?;
         }
         output_profile()?;
         next_frame += 1;
@@ -123,6 +144,8 @@ fn main() -> Result<()> {
         cache.fetch_frame(next_frame - 1)?
     };
     let start_simulation_time = current_state.time();
+    let mut statistics = FrameStatistics::start();
+    let mut substep_start_time = start_simulation_time;
 
     while run.load(Ordering::Relaxed)
         && number_of_sub_frames.is_none_or(|n| n > completed_sub_frames)
@@ -140,6 +163,8 @@ fn main() -> Result<()> {
         }
 
         completed_sub_frames += 1;
+        statistics.add_substep(current_state.time() - substep_start_time);
+        substep_start_time = current_state.time();
 
         info!(
                 "simulated_time: {:0.4}, real_time: {:0.4}, ratio: {:0.4}, per_subframe: {:0.4}, per_frame: {:0.4}",
@@ -157,7 +182,12 @@ fn main() -> Result<()> {
         }
 
         if !no_output {
-            cache.store_frame(current_state.clone())?;
+            cache.store_frame(
+                &current_state,
+                FrameContents::Checkpoint,
+                replace(&mut statistics, FrameStatistics::start()),
+                &report,
+            )?;
         }
         output_profile()?;
         next_frame += 1;
@@ -20,11 +20,16 @@ use anyhow::{Result, bail, ensure};
 use blended_mpm_api::T;
 use iter_enumeration::{IntoIterEnum2, IntoIterEnum3};
 use nalgebra::{Matrix3, Vector2, Vector3};
-use rayon::iter::{IntoParallelRefIterator, ParallelBridge, ParallelIterator};
+use rayon::{
+    iter::{IntoParallelRefIterator, ParallelBridge, ParallelIterator},
+    slice::ParallelSliceMut,
+};
 use serde::{Deserialize, Serialize};
 use tracing::{info, warn};
 
-use crate::math::{Aabb, basis_from_direction_3d};
+use crate::math::{Aabb, basis_from_direction_3d, random::random_vector};
+
+use super::winding_tree::WindingTree;
 use crate::{Report, ReportInfo, report::REPORT_STRIDE};
 
 #[derive(Debug, Default, Clone, Serialize, Deserialize)]
@@ -211,16 +216,18 @@ impl Mesh {
                     .iter_enum_2b()
             });
 
-        empty()
+        let samples = empty()
             .chain(vertex_samples)
             .chain(edge_samples)
             .chain(triangle_samples)
+            .enumerate()
             .par_bridge()
             .map(|sample| {
                 ensure!(run.load(Ordering::Relaxed), "Cancelled");
                 Ok(sample)
             })
-            .collect::<Result<_>>()
+            .collect::<Result<_>>()?;
+        Ok(restore_order(samples))
     }
 
     pub fn sample_inside(
@@ -229,6 +236,7 @@ impl Mesh {
         report: Report,
         spacing: T,
         randomness: T,
+        random_stream: u64,
     ) -> Result<Vec<Vector3<T>>> {
         ensure!(spacing != 0.);
 
@@ -241,48 +249,21 @@ impl Mesh {
         aabb.min += aabb.extents() * 0.001;
         aabb.max -= aabb.extents() * 0.001;
 
-        let to_positions = |[a, b, c]: &[u32; 3]| {
-            [
-                self.vertices[*a as usize],
-                self.vertices[*b as usize],
-                self.vertices[*c as usize],
-            ]
-        };
-
-        let wind_triangle = move |position, triangle: &[u32; 3]| {
-            let [a, b, c]: [Vector3<T>; 3] = to_positions(triangle).map(|x| x - position);
-
-            let ab = a.dot(&b);
-            let bc = b.dot(&c);
-            let ca = c.dot(&a);
-
-            let det_abc = Matrix3::from_columns(&[a, b, c]).determinant();
-
-            let a = a.norm();
-            let b = b.norm();
-            let c = c.norm();
-
-            let divisor = a * b * c + ab * c + bc * a + ca * b;
-
-            det_abc.atan2(divisor) / std::f64::consts::TAU as T
-        };
-
-        #[derive(Debug)]
         struct Cell {
             winding: T,
-            center: Vector3<T>,
             close_triangles: Vec<usize>,
         }
 
         let pre_compute_spacing = spacing * 10.;
         let close_measure = pre_compute_spacing * 2.;
+        let tree = WindingTree::new(self);
         let (count, cell_lattice) = aabb.lattice(pre_compute_spacing);
         let acceleration_report = report.new_sub(ReportInfo {
             name: "Build Acceleration Structure".to_string(),
             completed_steps: 0,
             steps_to_completion: NonZero::new((count / REPORT_STRIDE).max(1)).unwrap(),
         });
-        let cells: Vec<Cell> = cell_lattice
+        let cells = cell_lattice
             .enumerate()
             .par_bridge()
             .map(|(i, center)| {
@@ -290,29 +271,18 @@ impl Mesh {
                 if i % REPORT_STRIDE == 0 {
                     acceleration_report.step();
                 }
-                let cell = self.triangles.iter().enumerate().fold(
+                let (winding, close_triangles) = tree.far_winding(&center, close_measure);
+                Ok((
+                    i,
                     Cell {
-                        winding: 0.,
-                        center,
-                        close_triangles: Default::default(),
+                        winding,
+                        close_triangles,
                     },
-                    |mut cell, (triangle_idx, triangle)| {
-                        let Some(n) = self.triangle_normals[triangle_idx].as_ref() else {
-                            return cell;
-                        };
-                        let [a, b, c] = to_positions(triangle);
-                        if close_measure < point_to_triangle(&cell.center, &a, &b, &c, n) {
-                            cell.winding += wind_triangle(cell.center, triangle);
-                        } else {
-                            cell.close_triangles.push(triangle_idx);
-                        }
-
-                        cell
-                    },
-                );
-                Ok(cell)
+                ))
             })
             .collect::<Result<_>>()?;
+        // in lattice order, s.t. the closest cell can be indexed directly
+        let cells: Vec<Cell> = restore_order(cells);
         let average_ratio = cells
             .par_iter()
             .map(|cell| cell.close_triangles.len() as T / self.triangles.len() as T)
@@ -338,28 +308,27 @@ impl Mesh {
                 if i % REPORT_STRIDE == 0 {
                     samples_report.step();
                 }
-                candidate
+                (i, candidate)
             })
-            .map(|on_lattice| {
-                let random_offset =
-                    (Vector3::new_random() - Vector3::repeat(0.5)) * spacing * randomness;
-                on_lattice + random_offset
+            .map(|(i, on_lattice)| {
+                let random_offset = (random_vector(random_stream, i as u64) - Vector3::repeat(0.5))
+                    * spacing
+                    * randomness;
+                (i, on_lattice + random_offset)
             })
-            .filter(move |&candidate| {
-                let closest_cell = cells
-                    .iter()
-                    .min_by(|a, b| {
-                        let a = a.center - candidate;
-                        let b = b.center - candidate;
-                        a.norm_squared().total_cmp(&b.norm_squared())
-                    })
-                    .unwrap();
+            .filter(move |&(_, candidate)| {
+                let closest_cell =
+                    &cells[aabb.closest_lattice_index(pre_compute_spacing, &candidate)];
                 let winding = closest_cell.winding
                     + closest_cell
                         .close_triangles
                         .iter()
                         .map(|triangle_idx| {
-                            wind_triangle(candidate, &self.triangles[*triangle_idx])
+                            triangle_winding(
+                                &candidate,
+                                self.triangles[*triangle_idx]
+                                    .map(|vertex_idx| self.vertices[vertex_idx as usize]),
+                            )
                         })
                         .sum::<T>();
                 winding > 0.5
@@ -370,10 +339,35 @@ impl Mesh {
             })
             .collect::<Result<_>>()?;
 
-        Ok(samples)
+        Ok(restore_order(samples))
     }
 }
 
+// `par_bridge` doesn't preserve the order, but it should only depend on the input.
+fn restore_order<Item: Send>(mut enumerated: Vec<(usize, Item)>) -> Vec<Item> {
+    enumerated.par_sort_unstable_by_key(|(i, _)| *i);
+    enumerated.into_iter().map(|(_, item)| item).collect()
+}
+
+// Signed solid angle of the triangle seen from `position`, as a fraction of the full sphere.
+pub(super) fn triangle_winding(position: &Vector3<T>, triangle: [Vector3<T>; 3]) -> T {
+    let [a, b, c] = triangle.map(|x| x - position);
+
+    let ab = a.dot(&b);
+    let bc = b.dot(&c);
+    let ca = c.dot(&a);
+
+    let det_abc = Matrix3::from_columns(&[a, b, c]).determinant();
+
+    let a = a.norm();
+    let b = b.norm();
+    let c = c.norm();
+
+    let divisor = a * b * c + ab * c + bc * a + ca * b;
+
+    det_abc.atan2(divisor) / std::f64::consts::TAU as T
+}
+
 fn point_to_line(p: &Vector3<T>, a: &Vector3<T>, b: &Vector3<T>) -> T {
     let p_a = p - a;
     let b_a = b - a;
@@ -389,7 +383,7 @@ fn point_to_line(p: &Vector3<T>, a: &Vector3<T>, b: &Vector3<T>) -> T {
     d.norm()
 }
 
-fn point_to_triangle(
+pub(super) fn point_to_triangle(
     p: &Vector3<T>,
     a: &Vector3<T>,
     b: &Vector3<T>,
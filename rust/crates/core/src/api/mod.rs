@@ -10,6 +10,7 @@ pub mod mesh;
 pub mod serialization;
 pub mod serialized_vector;
 pub mod setup;
+mod winding_tree;
 
 pub use mesh::*;
 pub use serialization::*;
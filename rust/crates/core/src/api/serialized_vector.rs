@@ -14,7 +14,7 @@ use blended_mpm_api::T;
 use nalgebra::{Quaternion, Vector3};
 use serde::{Deserialize, Serialize};
 
-#[derive(Serialize, Deserialize)]
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
 pub struct SerializedVector {
     pub dtype: String,
     pub data: String,
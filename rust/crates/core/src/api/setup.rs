@@ -27,6 +27,15 @@ pub struct ObjectSettingsSolid {
     pub viscosity: T,
     pub dilation: T,
     pub randomness: T,
+    // mass-proportional damping, velocity decays with this rate (1/s)
+    #[serde(default)]
+    pub drag: T,
+    // stiffness-proportional damping, acts like a viscosity scaled with the stiffness (s)
+    #[serde(default)]
+    pub stiffness_damping: T,
+    // don't share grid nodes with other objects, resolve contact instead
+    #[serde(default)]
+    pub separate_velocity_field: bool,
 }
 
 #[derive(Clone, Serialize, Deserialize)]
@@ -37,12 +46,31 @@ pub struct ObjectSettingsFluid {
     pub bulk_modulus: T,
     pub dilation: T,
     pub randomness: T,
+    // mass-proportional damping, velocity decays with this rate (1/s)
+    #[serde(default)]
+    pub drag: T,
+    // stiffness-proportional damping, acts like a viscosity scaled with the bulk modulus (s)
+    #[serde(default)]
+    pub stiffness_damping: T,
+    // don't share grid nodes with other objects, resolve contact instead
+    #[serde(default)]
+    pub separate_velocity_field: bool,
 }
 
 #[derive(Clone, Serialize, Deserialize)]
 pub struct ObjectSettingsCollider {
     pub sticky_factor: T,
+    // kinetic friction, applied while sliding
     pub friction_factor: T,
+    // tangential speed below which sliding stops entirely, relative to the normal impulse
+    #[serde(default)]
+    pub static_friction_factor: T,
+    // fraction of the approaching normal velocity that is reflected
+    #[serde(default)]
+    pub restitution: T,
+    // separating normal velocity that is needed to leave the surface
+    #[serde(default)]
+    pub adhesion_velocity: T,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -73,6 +101,20 @@ pub struct GlobalSettings {
     pub particle_size: T,
     pub frames_per_second: u32,
     pub gravity: Vector3<T>,
+    // friction between objects with separate velocity fields
+    #[serde(default)]
+    pub contact_friction_factor: T,
+    // drag on all of the grid, velocity decays with this rate (1/s)
+    #[serde(default)]
+    pub air_drag: T,
+    // seeds the jitter of particle sampling
+    #[serde(default)]
+    pub seed: u64,
+    // accumulate in a fixed order, s.t. identical setups give bit-identical results,
+    // the grids always do since they're scattered over colored blocks, this sums the
+    // diagnostics in one
+    #[serde(default)]
+    pub deterministic: bool,
 }
 
 pub struct Setup {
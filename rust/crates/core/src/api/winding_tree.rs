@@ -0,0 +1,170 @@
+// SPDX-License-Identifier: MIT
+//
+// Copyright 2025  Algebraic UG (haftungsbeschränkt)
+//
+// Use of this source code is governed by an MIT-style
+// license that can be found in the LICENSE_MIT file or at
+// https://opensource.org/licenses/MIT.
+
+use std::ops::Range;
+
+use blended_mpm_api::T;
+use nalgebra::Vector3;
+
+use crate::math::Aabb;
+
+use super::mesh::{Mesh, point_to_triangle, triangle_winding};
+
+const LEAF_SIZE: usize = 8;
+// Subtrees further away than this many of their radii are approximated by a dipole.
+const FAR_FIELD_RATIO: T = 3.;
+
+struct Node {
+    aabb: Aabb<Vector3<T>>,
+    // area weighted center and sum of the area weighted normals
+    dipole_center: Vector3<T>,
+    dipole: Vector3<T>,
+    radius: T,
+    triangles: Range<usize>,
+    children: Option<[usize; 2]>,
+}
+
+// Bounding volume hierarchy over the triangles of a mesh for fast winding numbers,
+// see "Fast Winding Numbers for Soups and Clouds" by Barill et al.
+pub(super) struct WindingTree<'a> {
+    mesh: &'a Mesh,
+    // Triangles without a normal are left out like before.
+    // Reordered s.t. the triangles of a node are contiguous.
+    triangles: Vec<usize>,
+    nodes: Vec<Node>,
+    root: Option<usize>,
+}
+
+impl<'a> WindingTree<'a> {
+    pub fn new(mesh: &'a Mesh) -> Self {
+        let triangles: Vec<usize> = (0..mesh.triangles.len())
+            .filter(|triangle_idx| mesh.triangle_normals[*triangle_idx].is_some())
+            .collect();
+        let mut tree = Self {
+            mesh,
+            root: None,
+            nodes: Vec::with_capacity(2 * triangles.len() / LEAF_SIZE + 1),
+            triangles,
+        };
+        if !tree.triangles.is_empty() {
+            tree.root = Some(tree.build(0..tree.triangles.len()));
+        }
+        tree
+    }
+
+    fn positions(&self, triangle_idx: usize) -> [Vector3<T>; 3] {
+        positions(self.mesh, triangle_idx)
+    }
+
+    // Children are pushed before their parent, the returned index is the parent's.
+    fn build(&mut self, triangles: Range<usize>) -> usize {
+        let mut aabb = Aabb::default();
+        let mut dipole = Vector3::zeros();
+        let mut weighted_center = Vector3::zeros();
+        let mut area = 0.;
+        for triangle_idx in &self.triangles[triangles.clone()] {
+            let [a, b, c] = self.positions(*triangle_idx);
+            aabb = aabb.extend(a).extend(b).extend(c);
+            let area_normal = (b - a).cross(&(c - a)) * 0.5;
+            let triangle_area = area_normal.norm();
+            dipole += area_normal;
+            weighted_center += (a + b + c) * (triangle_area / 3.);
+            area += triangle_area;
+        }
+        let dipole_center = if area > 0. {
+            weighted_center / area
+        } else {
+            (aabb.min + aabb.max) * 0.5
+        };
+        let radius = self.triangles[triangles.clone()]
+            .iter()
+            .flat_map(|triangle_idx| self.positions(*triangle_idx))
+            .map(|vertex| (vertex - dipole_center).norm())
+            .fold(0., T::max);
+
+        let children = (triangles.len() > LEAF_SIZE).then(|| {
+            let axis = aabb.extents().imax();
+            let mesh = self.mesh;
+            let centroid = |triangle_idx: &usize| -> T {
+                positions(mesh, *triangle_idx).iter().map(|x| x[axis]).sum()
+            };
+            let mid = triangles.len() / 2;
+            self.triangles[triangles.clone()]
+                .select_nth_unstable_by(mid, |a, b| centroid(a).total_cmp(&centroid(b)));
+            let mid = triangles.start + mid;
+            [
+                self.build(triangles.start..mid),
+                self.build(mid..triangles.end),
+            ]
+        });
+
+        self.nodes.push(Node {
+            aabb,
+            dipole_center,
+            dipole,
+            radius,
+            triangles,
+            children,
+        });
+        self.nodes.len() - 1
+    }
+
+    // Winding number at `point` of all triangles further away than `close_distance`.
+    // The closer ones are returned to be evaluated exactly at a nearby point.
+    pub fn far_winding(&self, point: &Vector3<T>, close_distance: T) -> (T, Vec<usize>) {
+        let mut winding = 0.;
+        let mut close_triangles = Vec::new();
+        let mut stack: Vec<usize> = self.root.into_iter().collect();
+        while let Some(node_idx) = stack.pop() {
+            let node = &self.nodes[node_idx];
+            if node.aabb.distance(point) > close_distance {
+                winding += self.winding(node_idx, point);
+            } else if let Some(children) = node.children {
+                stack.extend(children);
+            } else {
+                for triangle_idx in &self.triangles[node.triangles.clone()] {
+                    let Some(normal) = &self.mesh.triangle_normals[*triangle_idx] else {
+                        continue;
+                    };
+                    let [a, b, c] = self.positions(*triangle_idx);
+                    if close_distance < point_to_triangle(point, &a, &b, &c, normal).abs() {
+                        winding += triangle_winding(point, [a, b, c]);
+                    } else {
+                        close_triangles.push(*triangle_idx);
+                    }
+                }
+            }
+        }
+        // the exact sum is then in mesh order
+        close_triangles.sort_unstable();
+        (winding, close_triangles)
+    }
+
+    fn winding(&self, node_idx: usize, point: &Vector3<T>) -> T {
+        let node = &self.nodes[node_idx];
+        let to_center = node.dipole_center - point;
+        let distance = to_center.norm();
+        if distance > FAR_FIELD_RATIO * node.radius {
+            to_center.dot(&node.dipole) / (2. * std::f64::consts::TAU as T * distance.powi(3))
+        } else if let Some(children) = node.children {
+            children
+                .into_iter()
+                .map(|child_idx| self.winding(child_idx, point))
+                .sum()
+        } else {
+            self.triangles[node.triangles.clone()]
+                .iter()
+                .map(|triangle_idx| triangle_winding(point, self.positions(*triangle_idx)))
+                .sum()
+        }
+    }
+}
+
+fn positions(mesh: &Mesh, triangle_idx: usize) -> [Vector3<T>; 3] {
+    mesh.triangles[triangle_idx].map(|vertex_idx| mesh.vertices[vertex_idx as usize])
+}
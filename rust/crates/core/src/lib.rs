@@ -22,7 +22,11 @@ mod report;
 mod simulation;
 
 pub use report::{Report, ReportInfo};
-pub use simulation::{Phase, PhaseInput, State, cache::Cache, weights};
+pub use simulation::{
+    FrameContents, Phase, PhaseInput, State,
+    cache::{Cache, FrameStatistics},
+    weights,
+};
 
 pub struct ContextImpl(BTreeMap<String, SimulationLocal>);
 
@@ -67,6 +71,7 @@ impl Context for ContextImpl {
         uuid: String,
         cache_dir: PathBuf,
         max_bytes_on_disk: u64,
+        force_unlock: bool,
     ) -> anyhow::Result<()> {
         let stamp = Instant::now();
         self.0.remove(&uuid);
@@ -74,7 +79,7 @@ impl Context for ContextImpl {
         self.0.insert(
             uuid.clone(),
             SimulationLocal::new(
-                Cache::load(uuid, cache_dir.clone(), max_bytes_on_disk)
+                Cache::load(uuid, cache_dir.clone(), max_bytes_on_disk, force_unlock)
                     .with_context(|| format!("failed to load cache: {cache_dir:?}"))?,
             ),
         );
@@ -6,7 +6,10 @@
 // license that can be found in the LICENSE_MIT file or at
 // https://opensource.org/licenses/MIT.
 
-use std::ops::{Add, Mul, Sub};
+use std::{
+    array::from_fn,
+    ops::{Add, Mul, Sub},
+};
 
 use blended_mpm_api::T;
 use nalgebra::{Vector2, Vector3};
@@ -61,7 +64,7 @@ impl AabbVector for Vector3<T> {
     }
 
     fn lattice(min: Self, extents: Self, spacing: T) -> (usize, impl Iterator<Item = Self>) {
-        let n = (extents / spacing).map(|x| x.max(1.) as usize);
+        let n = lattice_intervals(&extents, spacing);
         (
             n.product(),
             (0..=n.x).flat_map(move |i| {
@@ -79,6 +82,10 @@ impl AabbVector for Vector3<T> {
     }
 }
 
+fn lattice_intervals(extents: &Vector3<T>, spacing: T) -> Vector3<usize> {
+    (extents / spacing).map(|x| x.max(1.) as usize)
+}
+
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub struct Aabb<V: AabbVector> {
     pub min: V,
@@ -114,3 +121,24 @@ impl<V: AabbVector> Aabb<V> {
         V::lattice(self.min, self.extents(), spacing)
     }
 }
+
+impl Aabb<Vector3<T>> {
+    // zero for points inside
+    pub fn distance(&self, point: &Vector3<T>) -> T {
+        (self.min - point)
+            .sup(&(point - self.max))
+            .sup(&Vector3::zeros())
+            .norm()
+    }
+
+    // Index into the points of `lattice` without iterating them.
+    pub fn closest_lattice_index(&self, spacing: T, point: &Vector3<T>) -> usize {
+        let extents = self.extents();
+        let n = lattice_intervals(&extents, spacing);
+        let [i, j, k] = from_fn(|axis| {
+            let relative = (point[axis] - self.min[axis]) / extents[axis] * n[axis] as T;
+            (relative.round().max(0.) as usize).min(n[axis])
+        });
+        (i * (n.y + 1) + j) * (n.z + 1) + k
+    }
+}
@@ -10,6 +10,7 @@ mod aabb;
 mod basis_from_direction;
 mod consts;
 pub mod flat;
+pub mod random;
 pub mod safe_inverse;
 mod typedefs;
 
@@ -0,0 +1,36 @@
+// SPDX-License-Identifier: MIT
+//
+// Copyright 2025  Algebraic UG (haftungsbeschränkt)
+//
+// Use of this source code is governed by an MIT-style
+// license that can be found in the LICENSE_MIT file or at
+// https://opensource.org/licenses/MIT.
+
+use blended_mpm_api::T;
+use fxhash::hash64;
+use nalgebra::Vector3;
+
+const GOLDEN_GAMMA: u64 = 0x9e3779b97f4a7c15;
+
+// The k-th output of a splitmix64 generator is a pure function of seed and k.
+// This allows parallel consumers to draw numbers independent of the iteration order.
+fn splitmix64(seed: u64, k: u64) -> u64 {
+    let mut z = seed.wrapping_add(k.wrapping_add(1).wrapping_mul(GOLDEN_GAMMA));
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+    z ^ (z >> 31)
+}
+
+fn to_unit_interval(bits: u64) -> T {
+    ((bits >> 11) as f64 / (1u64 << 53) as f64) as T
+}
+
+// Derive an independent stream per object, names are stable across reordering.
+pub fn random_stream(seed: u64, name: &str) -> u64 {
+    splitmix64(seed, hash64(name))
+}
+
+// Components are uniform in [0, 1).
+pub fn random_vector(stream: u64, index: u64) -> Vector3<T> {
+    Vector3::from_fn(|i, _| to_unit_interval(splitmix64(stream, index * 3 + i as u64)))
+}
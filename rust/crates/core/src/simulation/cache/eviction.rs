@@ -0,0 +1,164 @@
+// SPDX-License-Identifier: MIT
+//
+// Copyright 2025  Algebraic UG (haftungsbeschränkt)
+//
+// Use of this source code is governed by an MIT-style
+// license that can be found in the LICENSE_MIT file or at
+// https://opensource.org/licenses/MIT.
+
+use std::{mem::replace, sync::atomic::Ordering};
+
+use anyhow::{Context, Result, bail};
+use blended_mpm_api::Task;
+use serde::{Deserialize, Serialize};
+use strum::IntoEnumIterator;
+use tracing::warn;
+
+use crate::simulation::state::{
+    columns::Column,
+    lean::{FrameContents, LeanAttribute},
+};
+
+use super::{
+    Cache,
+    frame::{encode_frame, write_frame},
+    frame_path,
+    manifest::write_manifest,
+};
+
+// What to give up once the disk budget is reached, the oldest frames go first.
+// The last checkpoint is always kept to continue from.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum EvictionPolicy {
+    // stop computing like before
+    #[default]
+    Fail,
+    // older checkpoints become lean frames
+    ThinCheckpoints,
+    // older frames lose their grids
+    DropGrids,
+    // older frames are evicted completely
+    RollingWindow,
+}
+
+impl EvictionPolicy {
+    // `None` if there's nothing to give up in the frame.
+    fn evict(
+        self,
+        contents: &FrameContents,
+        lean_attributes: &[LeanAttribute],
+    ) -> Option<FrameContents> {
+        match (self, contents) {
+            (Self::Fail, _) | (_, FrameContents::Evicted) => None,
+            (Self::ThinCheckpoints, FrameContents::Checkpoint) => {
+                Some(FrameContents::Lean(lean_attributes.to_vec()))
+            }
+            (Self::ThinCheckpoints, FrameContents::Lean(_)) => None,
+            (Self::DropGrids, contents) => contents.keeps(LeanAttribute::Grids).then(|| {
+                FrameContents::Lean(
+                    LeanAttribute::iter()
+                        .filter(|lean_attribute| {
+                            *lean_attribute != LeanAttribute::Grids
+                                && contents.keeps(*lean_attribute)
+                        })
+                        .collect(),
+                )
+            }),
+            (Self::RollingWindow, _) => Some(FrameContents::Evicted),
+        }
+    }
+}
+
+impl Cache {
+    // Another policy may give up more of the frames already evicted.
+    pub fn set_eviction_policy(&self, eviction_policy: EvictionPolicy) {
+        *self.eviction_policy.lock().unwrap() = eviction_policy;
+        self.eviction_cursor.store(0, Ordering::Relaxed);
+    }
+
+    // Rewrites the oldest frames until the cache is within the disk budget again.
+    // Frames before the cursor have nothing left to give up, so they aren't opened again.
+    pub(super) fn evict(&self) -> Result<()> {
+        let eviction_policy = *self.eviction_policy.lock().unwrap();
+        let over_budget = || {
+            self.bytes_on_disk.load(Ordering::Relaxed)
+                >= self.max_bytes_on_disk.load(Ordering::Relaxed)
+        };
+        if !over_budget() {
+            return Ok(());
+        }
+        if eviction_policy == EvictionPolicy::Fail {
+            bail!("Exceeding allowed disk space");
+        }
+
+        let available_frames = self.available_frames.load(Ordering::Relaxed);
+        let last_checkpoint = self.resume_frame(available_frames)?.checked_sub(1);
+        let lean_attributes = self.frame_policy().lean_attributes;
+        let codec = *self.frame_codec.lock().unwrap();
+        let cache_dir = self.cache_lock.cache_dir();
+
+        let mut evicted_any = false;
+        for frame in self.eviction_cursor.load(Ordering::Relaxed)..available_frames {
+            if !over_budget() {
+                break;
+            }
+            // the last checkpoint may be evicted once there's a newer one
+            if Some(frame) == last_checkpoint {
+                continue;
+            }
+            let mut reader = self.open_frame(frame)?;
+            if let Some(contents) = eviction_policy.evict(reader.contents(), &lean_attributes) {
+                let state = reader
+                    .read_state(Column::iter().filter(|column| column.is_stored(&contents)))
+                    .with_context(|| format!("decoding frame {frame} for eviction"))?;
+                drop(reader);
+
+                let evicted = contents == FrameContents::Evicted;
+                let encoded = encode_frame(&state, contents, codec)?;
+                let new_bytes = write_frame(frame_path(cache_dir, frame), &encoded)?;
+                let old_bytes = {
+                    let mut manifest = self.manifest.lock().unwrap();
+                    manifest[frame].evicted = evicted;
+                    replace(&mut manifest[frame].bytes, new_bytes)
+                };
+                self.bytes_on_disk.fetch_add(new_bytes, Ordering::Relaxed);
+                self.bytes_on_disk.fetch_sub(old_bytes, Ordering::Relaxed);
+                self.loaded_frames.lock().unwrap().drop_frame(frame);
+                self.evicted_frames.fetch_add(1, Ordering::Relaxed);
+                evicted_any = true;
+                warn!(
+                    frame,
+                    ?eviction_policy,
+                    "evicted frame to stay within the disk budget"
+                );
+            }
+            // frames after the last checkpoint are looked at again, it may still move
+            if Some(frame) < last_checkpoint {
+                self.eviction_cursor.store(frame + 1, Ordering::Relaxed);
+            }
+        }
+        // once for all evicted frames, loading catches up with a crash before
+        if evicted_any {
+            write_manifest(cache_dir, &self.manifest.lock().unwrap())
+                .context("manifest writing")?;
+        }
+        if over_budget() {
+            bail!("Exceeding allowed disk space, nothing left to evict with {eviction_policy:?}");
+        }
+        Ok(())
+    }
+
+    // A warning in the task tree once frames were evicted.
+    pub fn eviction_task(&self) -> Option<Task> {
+        let evicted_frames = self.evicted_frames.load(Ordering::Relaxed);
+        (evicted_frames > 0).then(|| Task {
+            name: format!(
+                "Warning: Evicted Frames to Stay Within the Disk Budget ({:?})",
+                *self.eviction_policy.lock().unwrap()
+            ),
+            completed_steps: evicted_frames,
+            steps_to_completion: self.available_frames.load(Ordering::Relaxed).max(1),
+            sub_tasks: Vec::new(),
+        })
+    }
+}
@@ -0,0 +1,391 @@
+// SPDX-License-Identifier: MIT
+//
+// Copyright 2025  Algebraic UG (haftungsbeschränkt)
+//
+// Use of this source code is governed by an MIT-style
+// license that can be found in the LICENSE_MIT file or at
+// https://opensource.org/licenses/MIT.
+
+use anyhow::{Context, Result, bail, ensure};
+use bincode::{deserialize_from, serialize_into};
+use build_info::VersionControl;
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::{File, rename},
+    io::{BufReader, Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+    process,
+    sync::atomic::{AtomicU64, Ordering},
+};
+use strum::IntoEnumIterator;
+
+use crate::{
+    State,
+    simulation::state::{columns::Column, lean::FrameContents},
+};
+
+use super::lock::process_alive;
+
+build_info::build_info!(fn build_info);
+
+// Frames start with the magic, the format version and the header.
+const FRAME_MAGIC: [u8; 4] = *b"BMPM";
+// Bump on any change to the serialized state, the header or the column table.
+// Frames of other versions aren't migrated, their caches have to be baked again,
+// only the bare states from before the header are read as checkpoints.
+const FRAME_VERSION: u32 = 1;
+const ZSTD_LEVEL: i32 = 3;
+const TEMP_SUFFIX: &str = ".tmp";
+// the magic and the version, anything smaller is a frame cut off by a crash
+pub const MIN_FRAME_BYTES: u64 = FRAME_MAGIC.len() as u64 + size_of::<u32>() as u64;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Codec {
+    #[default]
+    None,
+    // fast enough to keep up with computing
+    Lz4,
+    // smaller, but slower
+    Zstd,
+}
+
+// Stored states only decode with the same floating point types.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+enum Precision {
+    F32,
+    F64,
+    Mixed,
+}
+
+impl Precision {
+    fn of_build() -> Self {
+        if cfg!(feature = "f64") {
+            Self::F64
+        } else if cfg!(feature = "mixed_precision") {
+            Self::Mixed
+        } else {
+            Self::F32
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct FrameHeader {
+    precision: Precision,
+    build: String,
+    codec: Codec,
+    contents: FrameContents,
+}
+
+impl FrameHeader {
+    fn of_build(codec: Codec, contents: FrameContents) -> Self {
+        let build_info = build_info();
+        let commit = match &build_info.version_control {
+            Some(VersionControl::Git(git)) => git.commit_short_id.as_str(),
+            _ => "unknown commit",
+        };
+        Self {
+            precision: Precision::of_build(),
+            build: format!(
+                "{} {} ({commit})",
+                build_info.crate_info.name, build_info.crate_info.version
+            ),
+            codec,
+            contents,
+        }
+    }
+
+    // `None` for frames of the first version of the addon, they start with the bare state.
+    fn read<R: Read>(mut reader: R) -> Result<Option<Self>> {
+        let mut magic = [0; FRAME_MAGIC.len()];
+        if reader.read_exact(&mut magic).is_err() || magic != FRAME_MAGIC {
+            return Ok(None);
+        }
+        let version: u32 =
+            deserialize_from(&mut reader).context("frame version deserialization")?;
+        match version {
+            FRAME_VERSION => {}
+            version if version > FRAME_VERSION => bail!(
+                "Frame format version {version} is newer than the supported {FRAME_VERSION}, \
+                the cache was made by a newer version of the addon."
+            ),
+            version => bail!(
+                "Frame format version {version} is older than the supported {FRAME_VERSION}, \
+                the cache has to be baked again."
+            ),
+        }
+        deserialize_from(reader)
+            .map(Some)
+            .context("frame header deserialization")
+    }
+
+    fn baseline() -> Self {
+        Self {
+            precision: Precision::of_build(),
+            build: "the first version of the addon".to_string(),
+            codec: Codec::None,
+            contents: FrameContents::Checkpoint,
+        }
+    }
+
+    fn check(&self) -> Result<()> {
+        ensure!(
+            self.precision == Precision::of_build(),
+            "Frame was stored with {:?} precision by {}, but this build uses {:?} precision.",
+            self.precision,
+            self.build,
+            Precision::of_build(),
+        );
+        Ok(())
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct ColumnEntry {
+    column: Column,
+    // relative to the end of the table
+    offset: u64,
+    length: u64,
+    // crc32 of the compressed column
+    checksum: u32,
+}
+
+enum Layout {
+    // the bare state, without checksums
+    Baseline,
+    Columns(Vec<ColumnEntry>),
+}
+
+pub struct EncodedFrame {
+    pub bytes: Vec<u8>,
+    pub serialized_bytes: u64,
+}
+
+// Each column is compressed on its own s.t. it can be read alone.
+pub fn encode_frame(state: &State, contents: FrameContents, codec: Codec) -> Result<EncodedFrame> {
+    let mut table = Vec::new();
+    let mut data = Vec::new();
+    let mut serialized_bytes = 0;
+    for column in Column::iter().filter(|column| column.is_stored(&contents)) {
+        let serialized = state.column_bytes(column)?;
+        serialized_bytes += serialized.len() as u64;
+        let compressed = compress(codec, serialized)?;
+        table.push(ColumnEntry {
+            column,
+            offset: data.len() as u64,
+            length: compressed.len() as u64,
+            checksum: crc32fast::hash(&compressed),
+        });
+        data.extend(compressed);
+    }
+
+    let mut bytes = Vec::from(FRAME_MAGIC);
+    serialize_into(&mut bytes, &FRAME_VERSION).context("version serialization")?;
+    serialize_into(&mut bytes, &FrameHeader::of_build(codec, contents))
+        .context("header serialization")?;
+    serialize_into(&mut bytes, &table).context("column table serialization")?;
+    bytes.extend(data);
+    Ok(EncodedFrame {
+        bytes,
+        serialized_bytes,
+    })
+}
+
+// Frames are replaced through a temporary file, returns the bytes written.
+// Both are synced s.t. a crash leaves either the old or the new frame.
+pub fn write_frame<P: AsRef<Path>>(frame_path: P, frame: &EncodedFrame) -> Result<u64> {
+    let frame_path = frame_path.as_ref();
+    let temp_path = temp_path(frame_path);
+    let mut file = File::create(&temp_path).context("temp file creation")?;
+    file.write_all(&frame.bytes).context("frame file writing")?;
+    file.sync_all().context("frame file syncing")?;
+    let file_bytes = file.metadata().context("frame size")?.len();
+    drop(file);
+    rename(&temp_path, frame_path).context("frame file renaming")?;
+    if let Some(cache_dir) = frame_path.parent() {
+        sync_dir(cache_dir)?;
+    }
+    Ok(file_bytes)
+}
+
+// Unique per process and write, s.t. concurrent writers don't clobber each other.
+// `<file name>.<pid>.<write>.tmp`
+pub fn temp_path(path: &Path) -> PathBuf {
+    static WRITES: AtomicU64 = AtomicU64::new(0);
+    let mut file_name = path.file_name().unwrap_or_default().to_os_string();
+    file_name.push(format!(
+        ".{}.{}{TEMP_SUFFIX}",
+        process::id(),
+        WRITES.fetch_add(1, Ordering::Relaxed)
+    ));
+    path.with_file_name(file_name)
+}
+
+// Leftovers of writes interrupted by a crash, also of older versions.
+// Temporary files of a live process may still be renamed, even if they're in this cache.
+pub fn is_stale_temp_file(file_name: &str) -> bool {
+    if file_name == "temp.bin" || file_name == "evict.bin" {
+        return true;
+    }
+    let Some(stem) = file_name.strip_suffix(TEMP_SUFFIX) else {
+        return false;
+    };
+    let mut parts = stem.rsplit('.');
+    let write = parts.next().and_then(|write| write.parse::<u64>().ok());
+    let pid = parts.next().and_then(|pid| pid.parse::<u32>().ok());
+    write.is_some() && pid.is_some_and(|pid| !process_alive(pid))
+}
+
+// Makes the rename durable, directories can't be synced on windows.
+fn sync_dir(dir: &Path) -> Result<()> {
+    #[cfg(unix)]
+    File::open(dir)
+        .and_then(|dir| dir.sync_all())
+        .context("cache directory syncing")?;
+    #[cfg(not(unix))]
+    let _ = dir;
+    Ok(())
+}
+
+fn compress(codec: Codec, bytes: Vec<u8>) -> Result<Vec<u8>> {
+    Ok(match codec {
+        Codec::None => bytes,
+        Codec::Lz4 => lz4_flex::compress_prepend_size(&bytes),
+        Codec::Zstd => {
+            zstd::encode_all(bytes.as_slice(), ZSTD_LEVEL).context("zstd compression")?
+        }
+    })
+}
+
+fn decompress(codec: Codec, bytes: Vec<u8>) -> Result<Vec<u8>> {
+    Ok(match codec {
+        Codec::None => bytes,
+        Codec::Lz4 => lz4_flex::decompress_size_prepended(&bytes).context("lz4 decompression")?,
+        Codec::Zstd => zstd::decode_all(bytes.as_slice()).context("zstd decompression")?,
+    })
+}
+
+pub struct FrameReader<R> {
+    reader: R,
+    header: FrameHeader,
+    layout: Layout,
+    data_start: u64,
+}
+
+impl FrameReader<BufReader<File>> {
+    pub fn open<P: AsRef<Path>>(frame_path: P) -> Result<Self> {
+        Self::new(BufReader::new(
+            File::open(frame_path).context("opening frame")?,
+        ))
+    }
+}
+
+impl<R: Read + Seek> FrameReader<R> {
+    // Only reads the header and the column table.
+    pub fn new(mut reader: R) -> Result<Self> {
+        let (header, layout) = match FrameHeader::read(&mut reader)? {
+            Some(header) => {
+                header.check()?;
+                let table =
+                    deserialize_from(&mut reader).context("column table deserialization")?;
+                (header, Layout::Columns(table))
+            }
+            None => {
+                reader.rewind().context("rewinding frame")?;
+                (FrameHeader::baseline(), Layout::Baseline)
+            }
+        };
+        let data_start = reader.stream_position().context("frame position")?;
+        Ok(Self {
+            reader,
+            header,
+            layout,
+            data_start,
+        })
+    }
+
+    pub fn contents(&self) -> &FrameContents {
+        &self.header.contents
+    }
+
+    // Columns that aren't stored stay empty, baseline frames are read as a whole.
+    pub fn read_state<I: IntoIterator<Item = Column>>(&mut self, columns: I) -> Result<State> {
+        let Layout::Columns(table) = &self.layout else {
+            self.reader
+                .seek(SeekFrom::Start(self.data_start))
+                .context("seeking state")?;
+            let mut bytes = Vec::new();
+            self.reader
+                .read_to_end(&mut bytes)
+                .context("reading baseline frame")?;
+            return State::from_baseline_frame(&bytes).context(
+                "Frame of the first version of the addon couldn't be migrated, \
+                the cache has to be baked again.",
+            );
+        };
+
+        let mut columns: Vec<Column> = columns.into_iter().collect();
+        columns.sort_unstable();
+        columns.dedup();
+        let mut state = None;
+        for column in [Column::Objects].into_iter().chain(columns) {
+            let Some(entry) = table.iter().find(|entry| entry.column == column) else {
+                continue;
+            };
+            let bytes = read_column(&mut self.reader, self.data_start, entry)?;
+            let bytes = decompress(self.header.codec, bytes)?;
+            match &mut state {
+                None => state = Some(State::from_objects_column(&bytes)?),
+                Some(state) if column != Column::Objects => state.merge_column(column, &bytes)?,
+                Some(_) => {}
+            }
+        }
+        state.with_context(|| match self.header.contents {
+            FrameContents::Evicted => "Frame was evicted to stay within the disk budget",
+            _ => "Frame has no objects column",
+        })
+    }
+
+    // Checks the columns against their checksums, baseline frames are decoded instead.
+    pub fn verify(&mut self) -> Result<()> {
+        let Layout::Columns(table) = &self.layout else {
+            return self.read_state(Column::iter()).map(drop);
+        };
+        let data_end = table
+            .iter()
+            .map(|entry| entry.offset + entry.length)
+            .max()
+            .unwrap_or(0);
+        let file_end = self.reader.seek(SeekFrom::End(0)).context("seeking end")?;
+        ensure!(
+            file_end == self.data_start + data_end,
+            "Frame has {file_end} bytes instead of {}, it was truncated or appended to",
+            self.data_start + data_end
+        );
+        for entry in table {
+            read_column(&mut self.reader, self.data_start, entry)?;
+        }
+        Ok(())
+    }
+}
+
+// The column as stored, checked against its checksum.
+fn read_column<R: Read + Seek>(
+    reader: &mut R,
+    data_start: u64,
+    entry: &ColumnEntry,
+) -> Result<Vec<u8>> {
+    reader
+        .seek(SeekFrom::Start(data_start + entry.offset))
+        .context("seeking column")?;
+    let mut bytes = vec![0; entry.length as usize];
+    reader
+        .read_exact(&mut bytes)
+        .with_context(|| format!("reading column {:?}", entry.column))?;
+    ensure!(
+        crc32fast::hash(&bytes) == entry.checksum,
+        "Column {:?} doesn't match its checksum, the frame is corrupt",
+        entry.column
+    );
+    Ok(bytes)
+}
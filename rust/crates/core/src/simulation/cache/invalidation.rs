@@ -0,0 +1,142 @@
+// SPDX-License-Identifier: MIT
+//
+// Copyright 2025  Algebraic UG (haftungsbeschränkt)
+//
+// Use of this source code is governed by an MIT-style
+// license that can be found in the LICENSE_MIT file or at
+// https://opensource.org/licenses/MIT.
+
+use std::{fs::File, io::BufReader, path::Path};
+
+use anyhow::{Context, Result};
+use blended_mpm_api::T;
+use nalgebra::{Quaternion, Vector3};
+use serde::Serialize;
+use serde_json::{from_reader, to_value};
+use tracing::{info, warn};
+
+use crate::api::{BulkData, ObjectSettings, ObjectWithHandles, SerializedSetup};
+
+use super::setup_path;
+
+// The first frame that doesn't match the new setup anymore, `None` if all frames still do.
+// Only the animations of colliders are compared frame by frame, any other change
+// (settings, materials, meshes, resolution) invalidates all frames.
+pub(super) fn first_invalid_frame<P: AsRef<Path>>(
+    cache_dir: P,
+    setup: &SerializedSetup,
+) -> Result<Option<usize>> {
+    let path = setup_path(cache_dir);
+    if !path.is_file() {
+        return Ok(Some(0));
+    }
+    let read_stored_setup =
+        || -> Result<SerializedSetup> { Ok(from_reader(BufReader::new(File::open(&path)?))?) };
+    let stored_setup = match read_stored_setup() {
+        Ok(stored_setup) => stored_setup,
+        Err(e) => {
+            warn!("stored setup can't be compared, invalidating all frames: {e:?}");
+            return Ok(Some(0));
+        }
+    };
+
+    if differ(&stored_setup.settings, &setup.settings)?
+        || stored_setup.objects.len() != setup.objects.len()
+    {
+        return Ok(Some(0));
+    }
+
+    let mut first_invalid_frame = None;
+    for (stored, new) in stored_setup.objects.iter().zip(&setup.objects) {
+        if differ(&stored.object, &new.object)?
+            || !same_meshes(&stored_setup.bulk_data, stored, &setup.bulk_data, new)
+        {
+            info!(object = %new.object.name, "object changed");
+            return Ok(Some(0));
+        }
+        // only colliders are moved by their scripted frames
+        if !matches!(new.object.settings, ObjectSettings::Collider(_)) {
+            continue;
+        }
+        if let Some(frame) =
+            first_changed_scripted_frame(&stored_setup.bulk_data, stored, &setup.bulk_data, new)?
+        {
+            info!(
+                object = %new.object.name,
+                frame, "animation changed from scripted frame"
+            );
+            let frame = first_affected_frame(frame);
+            if first_invalid_frame.is_none_or(|first| frame < first) {
+                first_invalid_frame = Some(frame);
+            }
+        }
+    }
+    Ok(first_invalid_frame)
+}
+
+// The frame before already interpolates towards a changed scripted frame, a changed first
+// one moves the collider from the initial state on.
+fn first_affected_frame(scripted_frame: usize) -> usize {
+    match scripted_frame {
+        0 => 0,
+        scripted_frame => scripted_frame - 1,
+    }
+}
+
+fn differ<S: Serialize>(stored: &S, new: &S) -> Result<bool> {
+    Ok(to_value(stored)? != to_value(new)?)
+}
+
+fn same_meshes(
+    stored_bulk_data: &BulkData,
+    stored: &ObjectWithHandles,
+    bulk_data: &BulkData,
+    new: &ObjectWithHandles,
+) -> bool {
+    let stored_handles = &stored.mesh_handles;
+    let handles = &new.mesh_handles;
+    [
+        (&stored_handles.vertices, &handles.vertices),
+        (&stored_handles.triangles, &handles.triangles),
+        (&stored_handles.triangle_normals, &handles.triangle_normals),
+    ]
+    .into_iter()
+    .all(|(stored_handle, handle)| {
+        let stored = stored_bulk_data.serialized_vectors.get(stored_handle);
+        stored.is_some() && stored == bulk_data.serialized_vectors.get(handle)
+    })
+}
+
+// Also the first frame past the shorter animation if one is longer.
+fn first_changed_scripted_frame(
+    stored_bulk_data: &BulkData,
+    stored: &ObjectWithHandles,
+    bulk_data: &BulkData,
+    new: &ObjectWithHandles,
+) -> Result<Option<usize>> {
+    let scripted_frames = |bulk_data: &BulkData, object: &ObjectWithHandles| -> Result<_> {
+        let handles = &object.scripted_handles;
+        let vector = |handle: &String| {
+            bulk_data
+                .serialized_vectors
+                .get(handle)
+                .cloned()
+                .with_context(|| format!("missing bulk data: {handle}"))
+        };
+        let positions: Vec<Vector3<T>> = vector(&handles.scripted_positions)?.try_into()?;
+        let orientations: Vec<Quaternion<T>> =
+            vector(&handles.scripted_orientations)?.try_into()?;
+        Ok(positions.into_iter().zip(orientations).collect::<Vec<_>>())
+    };
+    let new_frames = scripted_frames(bulk_data, new)?;
+    let Ok(stored_frames) = scripted_frames(stored_bulk_data, stored) else {
+        return Ok(Some(0));
+    };
+
+    Ok(stored_frames
+        .iter()
+        .zip(&new_frames)
+        .position(|(stored, new)| stored != new)
+        .or((stored_frames.len() != new_frames.len())
+            .then(|| stored_frames.len().min(new_frames.len()))))
+}
@@ -0,0 +1,79 @@
+// SPDX-License-Identifier: MIT
+//
+// Copyright 2025  Algebraic UG (haftungsbeschränkt)
+//
+// Use of this source code is governed by an MIT-style
+// license that can be found in the LICENSE_MIT file or at
+// https://opensource.org/licenses/MIT.
+
+use std::collections::{BTreeSet, VecDeque};
+
+use anyhow::Result;
+
+use crate::{
+    State,
+    simulation::state::{columns::Column, lean::FrameContents},
+};
+
+// enough to scrub back and forth and to fetch from a few frames in turn
+pub(super) const CAPACITY: usize = 8;
+
+pub struct LoadedFrame {
+    pub frame: usize,
+    pub state: State,
+    pub contents: FrameContents,
+    columns: BTreeSet<Column>,
+}
+
+// The recently used frames with the columns read from them so far.
+#[derive(Default)]
+pub struct LoadedFrames {
+    // most recently used first
+    frames: VecDeque<LoadedFrame>,
+}
+
+impl LoadedFrames {
+    // `load` reads the given columns of the frame into a state.
+    pub fn get(
+        &mut self,
+        frame: usize,
+        columns: &[Column],
+        load: impl FnOnce(&BTreeSet<Column>) -> Result<(State, FrameContents)>,
+    ) -> Result<&LoadedFrame> {
+        let cached = self
+            .frames
+            .iter()
+            .position(|loaded| loaded.frame == frame)
+            .map(|idx| self.frames.remove(idx).unwrap());
+        let loaded = match cached {
+            Some(loaded) if columns.iter().all(|column| loaded.columns.contains(column)) => loaded,
+            cached => {
+                // the columns read before are read again with the new ones
+                let columns: BTreeSet<Column> = columns
+                    .iter()
+                    .copied()
+                    .chain(cached.into_iter().flat_map(|loaded| loaded.columns))
+                    .collect();
+                let (state, contents) = load(&columns)?;
+                LoadedFrame {
+                    frame,
+                    state,
+                    contents,
+                    columns,
+                }
+            }
+        };
+
+        self.frames.truncate(CAPACITY - 1);
+        self.frames.push_front(loaded);
+        Ok(&self.frames[0])
+    }
+
+    pub fn drop_frame(&mut self, frame: usize) {
+        self.frames.retain(|loaded| loaded.frame != frame);
+    }
+
+    pub fn drop_frames(&mut self, from_frame: usize) {
+        self.frames.retain(|loaded| loaded.frame < from_frame);
+    }
+}
@@ -6,41 +6,141 @@
 // license that can be found in the LICENSE_MIT file or at
 // https://opensource.org/licenses/MIT.
 
-use anyhow::{Result, bail, ensure};
+use anyhow::{Context, Result, bail, ensure};
+use serde::{Deserialize, Serialize};
+use serde_json::{from_str, to_string};
 use std::{
-    fs::{File, read_to_string, remove_file},
+    fs::{File, read_to_string, remove_file, rename},
     io::{ErrorKind, Write},
     path::{Path, PathBuf},
+    process,
+    sync::atomic::{AtomicU64, Ordering},
+    time::{SystemTime, UNIX_EPOCH},
 };
-use tracing::error;
+use tracing::{error, warn};
+
+use super::frame::temp_path;
+
+// `Cache::check` runs on every poll, the heartbeat is written less often.
+const HEARTBEAT_INTERVAL_SECS: u64 = 10;
 
 fn lock_path<P: AsRef<Path>>(cache_dir: P) -> PathBuf {
     cache_dir.as_ref().join("lock")
 }
 
+// The owner of the lock, older locks only contain the uuid.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub(super) struct LockInfo {
+    pub(super) uuid: String,
+    pub(super) pid: u32,
+    pub(super) hostname: String,
+    // seconds since the unix epoch
+    pub(super) started: u64,
+    pub(super) heartbeat: u64,
+}
+
+impl LockInfo {
+    fn read<P: AsRef<Path>>(lock_path: P) -> Result<Option<Self>> {
+        let contents = read_to_string(lock_path).context("reading lock file")?;
+        Ok(Self::parse(&contents))
+    }
+
+    // `None` for locks of older versions.
+    pub(super) fn parse(contents: &str) -> Option<Self> {
+        from_str(contents).ok()
+    }
+
+    // Replaces the lock file at once s.t. it's never read half written.
+    fn write<P: AsRef<Path>>(&self, cache_dir: P) -> Result<()> {
+        let lock_path = lock_path(cache_dir);
+        let temp_path = temp_path(&lock_path);
+        let mut file = File::create(&temp_path).context("lock file creation")?;
+        write!(&mut file, "{}", to_string(self)?).context("lock file writing")?;
+        rename(temp_path, lock_path).context("lock file renaming")?;
+        Ok(())
+    }
+
+    // Only a dead process on this host is known to not come back.
+    fn owner_is_dead(&self) -> bool {
+        self.hostname == hostname() && !process_alive(self.pid)
+    }
+
+    fn same_owner(&self, other: &Self) -> bool {
+        self.uuid == other.uuid && self.pid == other.pid
+    }
+}
+
+// Errors with the reason if the lock of `owner` can't be taken over.
+pub(super) fn check_takeover(
+    lock_path: &Path,
+    owner: Option<&LockInfo>,
+    force: bool,
+    now: u64,
+) -> Result<()> {
+    match owner {
+        _ if force => warn!(?owner, "forcefully taking over cache lock"),
+        Some(owner) if owner.owner_is_dead() => {
+            warn!(?owner, "taking over cache lock of a dead process")
+        }
+        Some(owner) => bail!(
+            "'lock' file exists: {lock_path:?}
+
+The cache is used by process {} on {}, its last heartbeat was {} seconds ago.
+If that process crashed on another computer, reload with the lock forcefully taken over.",
+            owner.pid,
+            owner.hostname,
+            now.saturating_sub(owner.heartbeat),
+        ),
+        None => bail!(
+            "'lock' file exists: {lock_path:?}
+
+It was written by an older version of the addon, so its owner is unknown.
+If no other simulation uses this cache, reload with the lock forcefully taken over."
+        ),
+    }
+    Ok(())
+}
+
 pub struct CacheLock {
     cache_dir: PathBuf,
-    uuid: String,
+    info: LockInfo,
+    last_heartbeat: AtomicU64,
 }
 
 impl CacheLock {
-    pub fn new<P: AsRef<Path>>(cache_dir: P, uuid: String) -> Result<Self> {
+    // `force` takes over the lock from any owner.
+    pub fn new<P: AsRef<Path>>(cache_dir: P, uuid: String, force: bool) -> Result<Self> {
         let lock_path = lock_path(&cache_dir);
-        let result = File::create_new(&lock_path);
-        if let Err(e) = &result
-            && e.kind() == ErrorKind::AlreadyExists
-        {
-            bail!(
-                "'lock' file exists: {lock_path:?}
+        let now = now();
+        let info = LockInfo {
+            uuid,
+            pid: process::id(),
+            hostname: hostname(),
+            started: now,
+            heartbeat: now,
+        };
 
-Either another simulation is currently using this cache
-or the lock is a remnant of a prior crash and you must delete it (sorry)."
-            );
+        match File::create_new(&lock_path) {
+            Ok(mut file) => {
+                write!(&mut file, "{}", to_string(&info)?).context("lock file writing")?
+            }
+            Err(e) if e.kind() == ErrorKind::AlreadyExists => {
+                let owner = LockInfo::read(&lock_path)?;
+                check_takeover(&lock_path, owner.as_ref(), force, now)?;
+                info.write(&cache_dir)?;
+                // another process may have taken it over at the same time
+                ensure!(
+                    LockInfo::read(&lock_path)?.is_some_and(|owner| owner.same_owner(&info)),
+                    "cache lock was taken over by another simulation at the same time"
+                );
+            }
+            Err(e) => return Err(e).context("lock file creation"),
         }
-        write!(&mut result?, "{uuid}")?;
+
         Ok(Self {
             cache_dir: cache_dir.as_ref().to_path_buf(),
-            uuid,
+            info,
+            last_heartbeat: AtomicU64::new(now),
         })
     }
 
@@ -48,19 +148,102 @@ or the lock is a remnant of a prior crash and you must delete it (sorry)."
         self.cache_dir.as_path()
     }
 
+    // Also refreshes the heartbeat s.t. other hosts can tell the lock is alive.
     pub fn check(&self) -> Result<()> {
+        let owner = LockInfo::read(lock_path(&self.cache_dir))?;
         ensure!(
-            self.uuid == read_to_string(lock_path(&self.cache_dir))?,
-            "cache lock's uuid has changed"
+            owner.is_some_and(|owner| owner.same_owner(&self.info)),
+            "cache lock was taken over by another simulation"
         );
+
+        let now = now();
+        let last_heartbeat = self.last_heartbeat.load(Ordering::Relaxed);
+        if now.saturating_sub(last_heartbeat) >= HEARTBEAT_INTERVAL_SECS
+            && self
+                .last_heartbeat
+                .compare_exchange(last_heartbeat, now, Ordering::Relaxed, Ordering::Relaxed)
+                .is_ok()
+        {
+            LockInfo {
+                uuid: self.info.uuid.clone(),
+                hostname: self.info.hostname.clone(),
+                heartbeat: now,
+                ..self.info
+            }
+            .write(&self.cache_dir)?;
+        }
         Ok(())
     }
 }
 
 impl Drop for CacheLock {
     fn drop(&mut self) {
+        // a lock that was taken over belongs to its new owner
+        if self.check().is_err() {
+            return;
+        }
         if let Err(e) = remove_file(lock_path(&self.cache_dir)) {
             error!("failed to clean up lock file: {e:?}");
         }
     }
 }
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs())
+}
+
+pub(super) fn hostname() -> String {
+    #[cfg(unix)]
+    {
+        let mut buffer = [0u8; 256];
+        // SAFETY: the buffer outlives the call and its length is passed along
+        let result = unsafe { libc::gethostname(buffer.as_mut_ptr().cast(), buffer.len()) };
+        if result == 0 {
+            let end = buffer.iter().position(|b| *b == 0).unwrap_or(buffer.len());
+            return String::from_utf8_lossy(&buffer[..end]).into_owned();
+        }
+    }
+    #[cfg(windows)]
+    if let Ok(hostname) = std::env::var("COMPUTERNAME") {
+        return hostname;
+    }
+    "unknown host".to_string()
+}
+
+// When in doubt the process is considered alive.
+#[cfg(unix)]
+pub(super) fn process_alive(pid: u32) -> bool {
+    let Ok(pid) = libc::pid_t::try_from(pid) else {
+        return true;
+    };
+    // SAFETY: signal 0 only checks whether the process exists
+    let result = unsafe { libc::kill(pid, 0) };
+    result == 0 || std::io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+}
+
+#[cfg(windows)]
+pub(super) fn process_alive(pid: u32) -> bool {
+    use windows_sys::Win32::{
+        Foundation::{CloseHandle, ERROR_ACCESS_DENIED, GetLastError, STILL_ACTIVE},
+        System::Threading::{GetExitCodeProcess, OpenProcess, PROCESS_QUERY_LIMITED_INFORMATION},
+    };
+    // SAFETY: the handle is checked before use and closed afterwards
+    unsafe {
+        let handle = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, 0, pid);
+        if handle.is_null() {
+            return GetLastError() == ERROR_ACCESS_DENIED;
+        }
+        let mut exit_code = 0;
+        let alive =
+            GetExitCodeProcess(handle, &mut exit_code) == 0 || exit_code == STILL_ACTIVE as u32;
+        CloseHandle(handle);
+        alive
+    }
+}
+
+#[cfg(not(any(unix, windows)))]
+pub(super) fn process_alive(_pid: u32) -> bool {
+    true
+}
@@ -0,0 +1,125 @@
+// SPDX-License-Identifier: MIT
+//
+// Copyright 2025  Algebraic UG (haftungsbeschränkt)
+//
+// Use of this source code is governed by an MIT-style
+// license that can be found in the LICENSE_MIT file or at
+// https://opensource.org/licenses/MIT.
+
+use std::{
+    fs::{File, OpenOptions, rename},
+    io::{BufRead, BufReader, BufWriter, Write},
+    path::{Path, PathBuf},
+    time::Instant,
+};
+
+use anyhow::{Context, Result};
+use blended_mpm_api::FrameMetadata;
+use serde_json::{from_str, to_string};
+use tracing::warn;
+
+use crate::State;
+
+use super::frame::temp_path;
+
+pub(super) fn manifest_path<P: AsRef<Path>>(cache_dir: P) -> PathBuf {
+    cache_dir.as_ref().join("manifest.jsonl")
+}
+
+// One line of json per frame like the diagnostics, `None` for caches stored before the manifest.
+// A crash can cut off the last line, the frames from there on aren't listed.
+pub(super) fn read_manifest<P: AsRef<Path>>(cache_dir: P) -> Result<Option<Vec<FrameMetadata>>> {
+    let path = manifest_path(cache_dir);
+    if !path.is_file() {
+        return Ok(None);
+    }
+    let mut manifest = Vec::new();
+    for line in BufReader::new(File::open(path).context("opening manifest file")?).lines() {
+        match from_str(&line.context("reading manifest line")?) {
+            Ok(metadata) => manifest.push(metadata),
+            Err(e) => {
+                warn!(frame = manifest.len(), "manifest is cut off: {e:?}");
+                break;
+            }
+        }
+    }
+    Ok(Some(manifest))
+}
+
+// Replaced through a temporary file, the frames it lists are already on disk.
+pub(super) fn write_manifest<P: AsRef<Path>>(
+    cache_dir: P,
+    manifest: &[FrameMetadata],
+) -> Result<()> {
+    let path = manifest_path(&cache_dir);
+    let temp_path = temp_path(&path);
+    let mut writer = BufWriter::new(File::create(&temp_path).context("manifest file creation")?);
+    for metadata in manifest {
+        writeln!(writer, "{}", to_string(metadata)?).context("manifest file writing")?;
+    }
+    writer
+        .into_inner()
+        .context("manifest file flushing")?
+        .sync_all()
+        .context("manifest file syncing")?;
+    rename(temp_path, path).context("manifest file renaming")?;
+    Ok(())
+}
+
+// The store thread appends each frame once it's on disk.
+pub(super) fn append_manifest<P: AsRef<Path>>(
+    cache_dir: P,
+    metadata: &FrameMetadata,
+) -> Result<()> {
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(manifest_path(cache_dir))
+        .context("opening manifest file")?;
+    writeln!(file, "{}", to_string(metadata)?).context("manifest file appending")?;
+    Ok(())
+}
+
+// Gathered while computing a frame, completed when the frame is stored.
+pub struct FrameStatistics {
+    start: Instant,
+    substeps: usize,
+    min_time_step: f64,
+    max_time_step: f64,
+}
+
+impl FrameStatistics {
+    pub fn start() -> Self {
+        Self {
+            start: Instant::now(),
+            substeps: 0,
+            min_time_step: 0.,
+            max_time_step: 0.,
+        }
+    }
+
+    pub fn add_substep(&mut self, time_step: f64) {
+        if self.substeps == 0 {
+            self.min_time_step = time_step;
+            self.max_time_step = time_step;
+        } else {
+            self.min_time_step = self.min_time_step.min(time_step);
+            self.max_time_step = self.max_time_step.max(time_step);
+        }
+        self.substeps += 1;
+    }
+
+    // The bytes are known once the store thread wrote the frame.
+    pub(super) fn metadata(&self, state: &State) -> FrameMetadata {
+        FrameMetadata {
+            time: state.time(),
+            substeps: self.substeps,
+            min_time_step: self.min_time_step,
+            max_time_step: self.max_time_step,
+            particles: state.number_of_particles(),
+            compute_seconds: self.start.elapsed().as_secs_f64(),
+            bytes: 0,
+            evicted: false,
+        }
+    }
+}
@@ -8,41 +8,84 @@
 
 use crate::api::{SerializedSetup, Setup};
 use anyhow::{Context, Result, ensure};
-use bincode::deserialize;
-use blended_mpm_api::T;
+use blended_mpm_api::{FrameMetadata, T, Task};
 use lock::CacheLock;
-use serde_json::{Value, from_reader, from_value, to_writer_pretty};
+use serde_json::{Value, from_reader, from_str, from_value, to_string, to_vec_pretty};
 use std::{
-    fs::{File, canonicalize, create_dir_all, metadata, read, read_dir, remove_file},
-    io::{BufWriter, Write},
+    collections::BTreeMap,
+    fs::{File, OpenOptions, canonicalize, create_dir_all, metadata, read_dir, remove_file},
+    io::{BufRead, BufReader, BufWriter, Write},
+    num::NonZero,
     path::{Path, PathBuf},
     sync::{
         Arc, Mutex,
         atomic::{AtomicU64, AtomicUsize, Ordering},
     },
 };
+use strum::IntoEnumIterator;
 use tracing::{debug, info, warn};
 
-use super::{State, state::attributes::Attribute};
+use crate::report::{Report, ReportInfo};
 
+use super::{
+    State,
+    state::{
+        attributes::{Attribute, AttributeDiagnostics},
+        columns::Column,
+        diagnostics::{Diagnostics, fetch_flat_diagnostics},
+        lean::{FrameContents, FramePolicy},
+        memory_usage::MemoryUsage,
+    },
+};
+
+mod eviction;
+mod frame;
+mod invalidation;
+mod loaded_frames;
 mod lock;
+mod manifest;
 mod store_thread;
-
+#[cfg(test)]
+mod tests;
+mod verify;
+
+pub use eviction::EvictionPolicy;
+pub use frame::Codec;
+use frame::{FrameReader, MIN_FRAME_BYTES, is_stale_temp_file};
+use invalidation::first_invalid_frame;
+use loaded_frames::{LoadedFrame, LoadedFrames};
+pub use manifest::FrameStatistics;
+use manifest::{manifest_path, read_manifest, write_manifest};
 pub use store_thread::StoreThread;
 
+// frames computed ahead of the store thread, each holding a state in memory
+pub const DEFAULT_STORE_QUEUE_DEPTH: usize = 2;
+
 pub struct Cache {
     pub setup: Arc<Setup>,
 
+    setup_bytes: u64,
     bytes_on_disk: Arc<AtomicU64>,
     max_bytes_on_disk: Arc<AtomicU64>,
-
-    loaded_frame: Mutex<Option<(usize, State)>>,
+    max_bytes_in_memory: AtomicU64,
+    store_queue_depth: AtomicUsize,
+    frame_codec: Mutex<Codec>,
+    frame_policy: Mutex<FramePolicy>,
+    eviction_policy: Mutex<EvictionPolicy>,
+    // frames before it have nothing left to give up under the eviction policy
+    eviction_cursor: AtomicUsize,
+    evicted_frames: AtomicUsize,
+
+    loaded_frames: Mutex<LoadedFrames>,
     cache_lock: CacheLock,
     available_frames: Arc<AtomicUsize>,
+    diagnostics: Arc<Mutex<Vec<Diagnostics>>>,
+    manifest: Arc<Mutex<Vec<FrameMetadata>>>,
     store_thread: Mutex<StoreThread>,
 }
 
 impl Cache {
+    // Only the frames that don't match the new setup anymore are dropped.
     pub fn new(
         uuid: String,
         setup: Value,
@@ -53,97 +96,139 @@ impl Cache {
         create_dir_all(&cache_dir).context("directory creation")?;
 
         info!("locking it");
-        let cache_lock = CacheLock::new(&cache_dir, uuid)?;
+        let cache_lock = CacheLock::new(&cache_dir, uuid, false)?;
 
         info!("parsing setup");
-        let parsed_setup = Arc::new(from_value::<SerializedSetup>(setup.clone())?.try_into()?);
-
-        info!("cleaning up old frames");
-        clean_up_frames(&cache_dir, 0).context("clean up old frames")?;
-
-        info!("write setup to disk");
-        let mut writer =
-            BufWriter::new(File::create(setup_path(&cache_dir)).context("setup file creation")?);
-        to_writer_pretty(&mut writer, &setup).context("setup file writing")?;
-        writer.flush().context("setup file flushing ")?;
-
-        let bytes_on_disk = Arc::new(AtomicU64::new(writer.into_inner()?.metadata()?.len()));
-        let max_bytes_on_disk = Arc::new(AtomicU64::new(max_bytes_on_disk));
-        let available_frames = Arc::new(AtomicUsize::new(0));
-
-        let store_thread = Mutex::new(StoreThread::new(
-            cache_dir,
-            bytes_on_disk.clone(),
-            available_frames.clone(),
-        ));
-
-        Ok(Self {
-            setup: parsed_setup,
+        let serialized_setup = from_value::<SerializedSetup>(setup.clone())?;
+
+        info!("comparing with the stored setup");
+        let first_invalid_frame = first_invalid_frame(&cache_dir, &serialized_setup)?;
+        let parsed_setup = Arc::new(serialized_setup.try_into()?);
+        let setup_json = to_vec_pretty(&setup).context("setup serialization")?;
+
+        if first_invalid_frame == Some(0) {
+            info!("cleaning up old frames");
+            clean_up_frames(&cache_dir, 0).context("clean up old frames")?;
+            write_diagnostics(&cache_dir, &[]).context("clean up old diagnostics")?;
+            write_manifest(&cache_dir, &[]).context("clean up old manifest")?;
+        }
 
-            bytes_on_disk,
+        let cache = Self::open(
+            cache_lock,
+            parsed_setup,
+            setup_json.len() as u64,
             max_bytes_on_disk,
+        )?;
+        if let Some(first_invalid_frame) = first_invalid_frame {
+            info!(
+                first_invalid_frame,
+                "dropping frames that don't match the setup"
+            );
+            cache.drop_frames(first_invalid_frame)?;
+        }
 
-            loaded_frame: None.into(),
-            cache_lock,
-            available_frames,
-            store_thread,
-        })
+        // only now, s.t. a failed drop leaves frames that still match the stored setup
+        info!("write setup to disk");
+        let mut file = File::create(setup_path(&cache_dir)).context("setup file creation")?;
+        file.write_all(&setup_json).context("setup file writing")?;
+        file.flush().context("setup file flushing")?;
+        Ok(cache)
     }
 
-    pub fn load(uuid: String, cache_dir: PathBuf, max_bytes_on_disk: u64) -> Result<Self> {
+    // `force_unlock` takes over the lock even if its owner might still be alive.
+    pub fn load(
+        uuid: String,
+        cache_dir: PathBuf,
+        max_bytes_on_disk: u64,
+        force_unlock: bool,
+    ) -> Result<Self> {
         info!("loading old cache: {:?}", canonicalize(&cache_dir));
 
         info!("locking it");
-        let cache_lock = CacheLock::new(&cache_dir, uuid)?;
+        let cache_lock = CacheLock::new(&cache_dir, uuid, force_unlock)?;
 
         info!("reading setup from disk");
         let setup = File::open(setup_path(&cache_dir)).context("opening setup file")?;
-        let mut bytes_on_disk = setup.metadata()?.len();
+        let setup_bytes = setup.metadata()?.len();
         let setup: SerializedSetup = from_reader(setup).context("reading setup file")?;
 
         info!("parsing setup");
         let setup = Arc::new(setup.try_into().context("parsing setup")?);
 
-        info!("discovering frames in cache");
-        let (bytes_on_disk_from_frames, frames) =
-            discover_frames(&cache_dir).context("discorvering frames")?;
-        bytes_on_disk += bytes_on_disk_from_frames;
+        Self::open(cache_lock, setup, setup_bytes, max_bytes_on_disk)
+    }
 
-        let mut frames = frames.into_iter().map(|(i, _)| i).collect::<Vec<_>>();
-        frames.sort();
-        ensure!(
-            frames.iter().enumerate().all(|(a, b)| a == *b),
-            "frames are missing"
-        );
+    // The frames in the manifest, `setup_bytes` is the size of the setup file.
+    fn open(
+        cache_lock: CacheLock,
+        setup: Arc<Setup>,
+        setup_bytes: u64,
+        max_bytes_on_disk: u64,
+    ) -> Result<Self> {
+        let cache_dir = cache_lock.cache_dir().to_path_buf();
+
+        info!("cleaning up interrupted writes");
+        clean_up_temp_files(&cache_dir).context("clean up temporary files")?;
+
+        info!("reading manifest");
+        let manifest = read_manifest(&cache_dir).context("reading manifest")?;
+        let manifest = check_manifest(&cache_dir, manifest).context("checking manifest")?;
+        // also drops a line cut off by a crash, s.t. appending continues on a new line
+        write_manifest(&cache_dir, &manifest).context("manifest writing")?;
+        let bytes_on_disk =
+            setup_bytes + manifest.iter().map(|metadata| metadata.bytes).sum::<u64>();
 
         let bytes_on_disk = Arc::new(AtomicU64::new(bytes_on_disk));
         let max_bytes_on_disk = Arc::new(AtomicU64::new(max_bytes_on_disk));
-        let available_frames = Arc::new(AtomicUsize::new(
-            frames
-                .iter()
-                .max()
-                .map(|max_frame| max_frame + 1)
-                .unwrap_or(0),
-        ));
+        let available_frames = Arc::new(AtomicUsize::new(manifest.len()));
         if available_frames.load(Ordering::Relaxed) == 0 {
             warn!("no frames recovered, need to build initial state");
         }
 
+        info!("reading diagnostics");
+        let mut diagnostics = read_diagnostics(&cache_dir).context("reading diagnostics")?;
+        if diagnostics.len() != available_frames.load(Ordering::Relaxed) {
+            warn!(
+                diagnostics = diagnostics.len(),
+                frames = available_frames.load(Ordering::Relaxed),
+                "diagnostics don't match the frames"
+            );
+            diagnostics.truncate(available_frames.load(Ordering::Relaxed));
+            write_diagnostics(&cache_dir, &diagnostics).context("truncating diagnostics")?;
+        }
+        let diagnostics = Arc::new(Mutex::new(diagnostics));
+        let manifest = Arc::new(Mutex::new(manifest));
+
         let store_thread = Mutex::new(StoreThread::new(
             cache_dir,
             bytes_on_disk.clone(),
             available_frames.clone(),
+            diagnostics.clone(),
+            manifest.clone(),
+            DEFAULT_STORE_QUEUE_DEPTH,
+            Codec::default(),
         ));
 
         Ok(Self {
             setup,
 
+            setup_bytes,
             bytes_on_disk,
             max_bytes_on_disk,
-
-            loaded_frame: None.into(),
+            // set when computing starts
+            max_bytes_in_memory: AtomicU64::new(u64::MAX),
+            store_queue_depth: AtomicUsize::new(DEFAULT_STORE_QUEUE_DEPTH),
+            frame_codec: Default::default(),
+            frame_policy: Default::default(),
+            eviction_policy: Default::default(),
+            eviction_cursor: AtomicUsize::new(0),
+            evicted_frames: AtomicUsize::new(0),
+
+            loaded_frames: Default::default(),
             cache_lock,
             available_frames,
+            diagnostics,
+            manifest,
             store_thread,
         })
     }
@@ -154,18 +239,28 @@ impl Cache {
 
     pub fn check(&self) -> Result<()> {
         self.cache_lock.check()?;
-        metadata(setup_path(self.cache_lock.cache_dir())).context("meta data of setup")?;
-        let mut frames = discover_frames(self.cache_lock.cache_dir())
-            .context("discovering frames")?
-            .1
-            .into_iter()
-            .map(|(i, _)| i)
-            .collect::<Vec<_>>();
-        frames.sort();
-        ensure!(
-            frames.iter().enumerate().all(|(a, b)| a == *b),
-            "frames are missing"
-        );
+        let cache_dir = self.cache_lock.cache_dir();
+        metadata(setup_path(cache_dir)).context("meta data of setup")?;
+        if manifest_path(cache_dir).is_file() {
+            // the store thread lists a frame before it's available, dropping unlists it after
+            ensure!(
+                self.manifest.lock().unwrap().len()
+                    >= self.available_frames.load(Ordering::Relaxed),
+                "frames are missing in the manifest"
+            );
+        } else {
+            let mut frames = discover_frames(cache_dir)
+                .context("discovering frames")?
+                .1
+                .into_iter()
+                .map(|(i, _)| i)
+                .collect::<Vec<_>>();
+            frames.sort();
+            ensure!(
+                frames.iter().enumerate().all(|(a, b)| a == *b),
+                "frames are missing"
+            );
+        }
         /* TODO: this check can fail.. we don't stop the store thread
         ensure!(
             self.available_frames.load(Ordering::Relaxed)
@@ -186,65 +281,171 @@ impl Cache {
             .store(max_bytes_on_disk, Ordering::Relaxed);
     }
 
-    pub fn store_frame(&self, state: State) -> Result<()> {
+    pub fn set_max_bytes_in_memory(&self, max_bytes_in_memory: u64) {
+        self.max_bytes_in_memory
+            .store(max_bytes_in_memory, Ordering::Relaxed);
+    }
+
+    // takes effect when the store thread is restarted by `drop_frames`
+    pub fn set_store_queue_depth(&self, store_queue_depth: usize) {
+        self.store_queue_depth
+            .store(store_queue_depth.max(1), Ordering::Relaxed);
+    }
+
+    // Also takes effect with `drop_frames`, frames already stored keep their codec.
+    pub fn set_frame_codec(&self, frame_codec: Codec) {
+        *self.frame_codec.lock().unwrap() = frame_codec;
+    }
+
+    pub fn set_frame_policy(&self, frame_policy: FramePolicy) {
+        *self.frame_policy.lock().unwrap() = frame_policy;
+    }
+
+    pub fn frame_policy(&self) -> FramePolicy {
+        self.frame_policy.lock().unwrap().clone()
+    }
+
+    pub fn max_bytes_in_memory(&self) -> u64 {
+        self.max_bytes_in_memory.load(Ordering::Relaxed)
+    }
+
+    // Includes the states waiting to be stored, returns the bytes in use.
+    pub fn check_memory(&self, memory_usage: &MemoryUsage) -> Result<u64> {
+        let queued_bytes = self.store_thread.lock().unwrap().queued_bytes();
+        let bytes_in_memory = memory_usage.total() + queued_bytes;
+        let to_giga_bytes = |bytes: u64| bytes as f64 / (1 << 30) as f64;
         ensure!(
-            self.bytes_on_disk.load(Ordering::Relaxed)
-                < self.max_bytes_on_disk.load(Ordering::Relaxed),
-            "Exceeding allowed disk space"
+            bytes_in_memory <= self.max_bytes_in_memory(),
+            "Exceeding allowed memory: {:.2} of {:.2} gigabytes, {:.2} of them waiting to be stored. \
+            Is the grid node size too small for the scene? {memory_usage:?}",
+            to_giga_bytes(bytes_in_memory),
+            to_giga_bytes(self.max_bytes_in_memory()),
+            to_giga_bytes(queued_bytes),
         );
-        self.store_thread.lock().unwrap().store(state)
+        Ok(bytes_in_memory)
     }
 
-    fn load_frame(&self, loaded_frame: &mut Option<(usize, State)>, frame: usize) -> Result<()> {
-        if loaded_frame
-            .as_ref()
-            .is_some_and(|(loaded_frame, _)| *loaded_frame == frame)
-        {
-            return Ok(());
-        }
+    // Evicts older frames when over the disk budget.
+    // Blocks while the store queue is full, which is shown as a sub report.
+    pub fn store_frame(
+        &self,
+        state: &State,
+        contents: FrameContents,
+        statistics: FrameStatistics,
+        report: &Report,
+    ) -> Result<()> {
+        self.evict()?;
+        // diagnostics need the complete state
+        let diagnostics = state.diagnostics(self.setup.settings.deterministic);
+        let metadata = statistics.metadata(state);
+        let stored = state.to_stored(&contents);
+        let (sender, queue_full) = {
+            let store_thread = self.store_thread.lock().unwrap();
+            (store_thread.sender(), store_thread.queue_full())
+        };
+        let _waiting_report = queue_full.then(|| {
+            report.new_sub(ReportInfo {
+                name: "Waiting for Frames to be Stored".to_string(),
+                completed_steps: 0,
+                steps_to_completion: NonZero::new(1).unwrap(),
+            })
+        });
+        sender.store(stored, contents, diagnostics, metadata)
+    }
 
+    pub fn store_task(&self) -> Task {
+        self.store_thread.lock().unwrap().task()
+    }
+
+    fn open_frame(&self, frame: usize) -> Result<FrameReader<BufReader<File>>> {
         ensure!(
             frame < self.available_frames.load(Ordering::Relaxed),
             "frame not computed yet"
         );
+        FrameReader::open(frame_path(self.cache_lock.cache_dir(), frame))
+            .with_context(|| format!("opening frame {frame}"))
+    }
 
-        debug!(frame, "reading frame from disk");
-        *loaded_frame = Some((
-            frame,
-            deserialize::<State>(
-                &read(frame_path(self.cache_lock.cache_dir(), frame)).context("read frame")?,
-            )
-            .context("decoding frame")?,
-        ));
-
-        Ok(())
+    // Reads only the columns needed, recently used frames are kept.
+    fn with_loaded_frame<R>(
+        &self,
+        frame: usize,
+        columns: &[Column],
+        f: impl FnOnce(&LoadedFrame) -> Result<R>,
+    ) -> Result<R> {
+        let mut loaded_frames = self.loaded_frames.lock().unwrap();
+        let loaded = loaded_frames.get(frame, columns, |columns| {
+            debug!(frame, ?columns, "reading frame from disk");
+            let mut reader = self.open_frame(frame)?;
+            let state = reader
+                .read_state(columns.iter().copied())
+                .with_context(|| format!("decoding frame {frame}"))?;
+            Ok((state, reader.contents().clone()))
+        })?;
+        f(loaded)
     }
 
+    // Computing can only continue from checkpoints.
     pub fn fetch_frame(&self, frame: usize) -> Result<State> {
-        let mut loaded_frame = self.loaded_frame.lock().unwrap();
-        self.load_frame(&mut loaded_frame, frame)?;
-        Ok(loaded_frame.as_ref().unwrap().1.clone())
+        let mut reader = self.open_frame(frame)?;
+        ensure!(
+            *reader.contents() == FrameContents::Checkpoint,
+            "frame {frame} isn't a checkpoint"
+        );
+        let mut state = reader
+            .read_state(Column::iter())
+            .with_context(|| format!("decoding frame {frame}"))?;
+        state.update_scripted_movements(&self.setup)?;
+        Ok(state)
     }
 
-    pub fn available_attributes(&self, frame: usize) -> Result<Vec<Attribute>> {
-        let mut loaded_frame = self.loaded_frame.lock().unwrap();
-        self.load_frame(&mut loaded_frame, frame)?;
-        Ok(loaded_frame
-            .as_ref()
+    // The frame computing continues from to compute `next_frame`, one after the last checkpoint.
+    pub fn resume_frame(&self, next_frame: usize) -> Result<usize> {
+        for frame in (0..next_frame).rev() {
+            if *self.open_frame(frame)?.contents() == FrameContents::Checkpoint {
+                return Ok(frame + 1);
+            }
+        }
+        Ok(0)
+    }
+
+    // Evicted frames only have their header left, there's nothing to show for them.
+    pub fn is_evicted(&self, frame: usize) -> bool {
+        self.manifest
+            .lock()
             .unwrap()
-            .1
-            .available_attributes()
-            .collect())
+            .get(frame)
+            .is_some_and(|metadata| metadata.evicted)
+    }
+
+    pub fn available_attributes(&self, frame: usize) -> Result<Vec<Attribute>> {
+        self.with_loaded_frame(frame, &[Column::Objects], |loaded| {
+            Ok(loaded
+                .state
+                .available_attributes()
+                .filter(|attribute| loaded.contents.contains(attribute))
+                .collect())
+        })
     }
 
     pub fn fetch_flat_attribute(&self, frame: usize, attribute: Attribute) -> Result<Vec<T>> {
-        let mut loaded_frame = self.loaded_frame.lock().unwrap();
-        self.load_frame(&mut loaded_frame, frame)?;
-        loaded_frame
-            .as_ref()
-            .unwrap()
-            .1
-            .fetch_flat_attribute(self.setup.settings.grid_node_size, attribute)
+        self.with_loaded_frame(frame, &Column::for_attribute(&attribute), |loaded| {
+            ensure!(
+                loaded.contents.contains(&attribute),
+                "Attribute isn't stored in lean frame {frame}, keep it or make it a checkpoint"
+            );
+            loaded
+                .state
+                .fetch_flat_attribute(self.setup.settings.grid_node_size, attribute)
+        })
+    }
+
+    pub fn manifest(&self) -> Vec<FrameMetadata> {
+        self.manifest.lock().unwrap().clone()
+    }
+
+    pub fn fetch_flat_diagnostics(&self, attribute: AttributeDiagnostics) -> Vec<T> {
+        fetch_flat_diagnostics(&self.diagnostics.lock().unwrap(), attribute)
     }
 
     pub fn drop_frames(&self, from_frame: usize) -> Result<()> {
@@ -253,16 +454,28 @@ impl Cache {
             self.cache_lock.cache_dir().to_path_buf(),
             self.bytes_on_disk.clone(),
             self.available_frames.clone(),
+            self.diagnostics.clone(),
+            self.manifest.clone(),
+            self.store_queue_depth.load(Ordering::Relaxed),
+            *self.frame_codec.lock().unwrap(),
         );
         self.available_frames
             .fetch_min(from_frame, Ordering::Relaxed);
+        self.eviction_cursor
+            .fetch_min(from_frame, Ordering::Relaxed);
+        self.loaded_frames.lock().unwrap().drop_frames(from_frame);
         clean_up_frames(self.cache_lock.cache_dir(), from_frame)?;
 
+        let mut diagnostics = self.diagnostics.lock().unwrap();
+        diagnostics.truncate(from_frame);
+        write_diagnostics(self.cache_lock.cache_dir(), &diagnostics)?;
+
+        let mut manifest = self.manifest.lock().unwrap();
+        manifest.truncate(from_frame);
+        write_manifest(self.cache_lock.cache_dir(), &manifest)?;
+
         self.bytes_on_disk.store(
-            metadata(setup_path(self.cache_lock.cache_dir()))
-                .context("meta data of setup")?
-                .len()
-                + discover_frames(self.cache_lock.cache_dir())?.0,
+            self.setup_bytes + manifest.iter().map(|metadata| metadata.bytes).sum::<u64>(),
             Ordering::Relaxed,
         );
         Ok(())
@@ -273,6 +486,44 @@ fn setup_path<P: AsRef<Path>>(cache_dir: P) -> PathBuf {
     cache_dir.as_ref().join("setup.json")
 }
 
+fn diagnostics_path<P: AsRef<Path>>(cache_dir: P) -> PathBuf {
+    cache_dir.as_ref().join("diagnostics.jsonl")
+}
+
+// One line of json per frame, s.t. the store thread can simply append.
+fn read_diagnostics<P: AsRef<Path>>(cache_dir: P) -> Result<Vec<Diagnostics>> {
+    let path = diagnostics_path(cache_dir);
+    if !path.is_file() {
+        warn!("no diagnostics found");
+        return Ok(Vec::new());
+    }
+    BufReader::new(File::open(path).context("opening diagnostics file")?)
+        .lines()
+        .map(|line| Ok(from_str(&line.context("reading diagnostics line")?)?))
+        .collect()
+}
+
+fn write_diagnostics<P: AsRef<Path>>(cache_dir: P, diagnostics: &[Diagnostics]) -> Result<()> {
+    let mut writer = BufWriter::new(
+        File::create(diagnostics_path(cache_dir)).context("diagnostics file creation")?,
+    );
+    for diagnostics in diagnostics {
+        writeln!(writer, "{}", to_string(diagnostics)?).context("diagnostics file writing")?;
+    }
+    writer.flush().context("diagnostics file flushing")?;
+    Ok(())
+}
+
+fn append_diagnostics<P: AsRef<Path>>(cache_dir: P, diagnostics: &Diagnostics) -> Result<()> {
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(diagnostics_path(cache_dir))
+        .context("opening diagnostics file")?;
+    writeln!(file, "{}", to_string(diagnostics)?).context("diagnostics file appending")?;
+    Ok(())
+}
+
 fn frame_path<P: AsRef<Path>>(cache_dir: P, frame: usize) -> PathBuf {
     cache_dir.as_ref().join(format!("frame_{frame:05}.bin"))
 }
@@ -321,3 +572,100 @@ fn clean_up_frames<P: AsRef<Path>>(cache_dir: P, from_frame: usize) -> Result<()
     }
     Ok(())
 }
+
+// Lists the frames on disk from the first one, up to one that's missing or cut off by a crash.
+// Those and frames that aren't listed, e.g. after a crash before appending to the manifest,
+// are removed. Caches stored before the manifest only know the frames and their times.
+fn check_manifest<P: AsRef<Path>>(
+    cache_dir: P,
+    manifest: Option<Vec<FrameMetadata>>,
+) -> Result<Vec<FrameMetadata>> {
+    let frame_bytes = discover_frames(&cache_dir)
+        .context("discovering frames")?
+        .1
+        .into_iter()
+        .map(|(frame, frame_path)| {
+            Ok((
+                frame,
+                metadata(frame_path).context("reading file size")?.len(),
+            ))
+        })
+        .collect::<Result<BTreeMap<usize, u64>>>()?;
+
+    let mut manifest = match manifest {
+        Some(manifest) => manifest,
+        None => {
+            warn!("no manifest found, discovering frames in cache");
+            let diagnostics = read_diagnostics(&cache_dir).context("reading diagnostics")?;
+            (0..frame_bytes.len())
+                .map(|frame| FrameMetadata {
+                    time: diagnostics
+                        .get(frame)
+                        .map_or(0., |diagnostics| diagnostics.time),
+                    bytes: frame_bytes.get(&frame).copied().unwrap_or(0),
+                    ..Default::default()
+                })
+                .collect()
+        }
+    };
+
+    let mut intact_frames = 0;
+    for (frame, listed) in manifest.iter_mut().enumerate() {
+        match frame_bytes.get(&frame) {
+            Some(bytes) if *bytes >= MIN_FRAME_BYTES && listed.bytes == *bytes => {
+                intact_frames += 1;
+            }
+            // evicted right before a crash, the frame was replaced as a whole
+            Some(bytes) if *bytes >= MIN_FRAME_BYTES => {
+                let Some(contents) = intact_frame_contents(frame_path(&cache_dir, frame)) else {
+                    warn!(frame, bytes, listed = listed.bytes, "frame was cut off");
+                    break;
+                };
+                warn!(frame, bytes, listed = listed.bytes, "frame size changed");
+                listed.bytes = *bytes;
+                listed.evicted = contents == FrameContents::Evicted;
+                intact_frames += 1;
+            }
+            Some(_) => {
+                warn!(frame, "frame was cut off");
+                break;
+            }
+            None => {
+                warn!(frame, "frame is missing");
+                break;
+            }
+        }
+    }
+    if intact_frames < manifest.len() || frame_bytes.range(intact_frames..).next().is_some() {
+        warn!(
+            intact_frames,
+            "removing the frames after the last intact one"
+        );
+        manifest.truncate(intact_frames);
+        clean_up_frames(&cache_dir, intact_frames)?;
+    }
+    Ok(manifest)
+}
+
+// `None` if the frame doesn't match its column table, e.g. cut off in a column.
+fn intact_frame_contents<P: AsRef<Path>>(frame_path: P) -> Option<FrameContents> {
+    let mut reader = FrameReader::open(frame_path).ok()?;
+    reader.verify().ok()?;
+    Some(reader.contents().clone())
+}
+
+fn clean_up_temp_files<P: AsRef<Path>>(cache_dir: P) -> Result<()> {
+    for entry in read_dir(cache_dir).context("reading cache directory")? {
+        let path = entry.context("getting_dir entry")?.path();
+        if path.is_file()
+            && path
+                .file_name()
+                .and_then(|file_name| file_name.to_str())
+                .is_some_and(is_stale_temp_file)
+        {
+            warn!("removing temporary file of an interrupted write: {path:?}");
+            remove_file(path)?
+        }
+    }
+    Ok(())
+}
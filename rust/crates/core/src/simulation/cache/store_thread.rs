@@ -7,53 +7,148 @@
 // https://opensource.org/licenses/MIT.
 
 use anyhow::{Context, Result, bail};
-use bincode::serialize;
+use blended_mpm_api::{FrameMetadata, Task};
 use std::{
-    fs::{File, rename},
-    io::Write,
     path::PathBuf,
     sync::{
-        Arc,
+        Arc, Mutex,
         atomic::{AtomicU64, AtomicUsize, Ordering},
-        mpsc::{Sender, channel},
+        mpsc::{SyncSender, sync_channel},
     },
     thread::{JoinHandle, spawn},
+    time::{Duration, Instant},
 };
 use tracing::{debug, info};
 
-use crate::State;
+use crate::{
+    State,
+    simulation::state::{diagnostics::Diagnostics, lean::FrameContents},
+};
 
-use super::frame_path;
+use super::{
+    append_diagnostics,
+    frame::{Codec, encode_frame, write_frame},
+    frame_path,
+    manifest::append_manifest,
+};
 
 pub struct StoreThread {
-    store_tx: Sender<State>,
+    store_tx: SyncSender<QueuedFrame>,
+    queue_depth: usize,
+    statistics: Arc<StoreStatistics>,
     thread: Option<JoinHandle<Result<()>>>,
 }
 
+#[derive(Default)]
+struct StoreStatistics {
+    queued_states: AtomicUsize,
+    // states waiting to be written still count towards the memory budget
+    queued_bytes: AtomicU64,
+    bytes_written: AtomicU64,
+    // before compression
+    bytes_serialized: AtomicU64,
+    nanos_writing: AtomicU64,
+}
+
+struct QueuedFrame {
+    state: State,
+    contents: FrameContents,
+    // of the complete state, lean frames can't compute them
+    diagnostics: Diagnostics,
+    // completed with the bytes written
+    metadata: FrameMetadata,
+    state_bytes: u64,
+}
+
+pub struct StoreSender {
+    store_tx: SyncSender<QueuedFrame>,
+    statistics: Arc<StoreStatistics>,
+}
+
+impl StoreSender {
+    pub fn store(
+        self,
+        state: State,
+        contents: FrameContents,
+        diagnostics: Diagnostics,
+        metadata: FrameMetadata,
+    ) -> Result<()> {
+        let state_bytes = state.memory_usage().total();
+        self.statistics
+            .queued_states
+            .fetch_add(1, Ordering::Relaxed);
+        self.statistics
+            .queued_bytes
+            .fetch_add(state_bytes, Ordering::Relaxed);
+        Ok(self.store_tx.send(QueuedFrame {
+            state,
+            contents,
+            diagnostics,
+            metadata,
+            state_bytes,
+        })?)
+    }
+}
+
 impl StoreThread {
     pub fn new(
         cache_dir: PathBuf,
         bytes_on_disk: Arc<AtomicU64>,
         available_frames: Arc<AtomicUsize>,
+        diagnostics: Arc<Mutex<Vec<Diagnostics>>>,
+        manifest: Arc<Mutex<Vec<FrameMetadata>>>,
+        queue_depth: usize,
+        codec: Codec,
     ) -> Self {
-        info!("starting store thread");
-        let temp_file_path = cache_dir.join("temp.bin");
-        let (store_tx, store_rx) = channel();
+        info!(queue_depth, ?codec, "starting store thread");
+        // The state being written isn't in the queue anymore, so it's one more in memory.
+        let (store_tx, store_rx) = sync_channel(queue_depth.saturating_sub(1));
+        let statistics = Arc::new(StoreStatistics::default());
+        let thread_statistics = statistics.clone();
         let thread = Some(spawn(move || -> Result<()> {
-            while let Ok(state) = store_rx.recv() {
-                let mut file = File::create(&temp_file_path).context("temp file creation")?;
-                file.write_all(&serialize(&state).context("state serialization")?)
-                    .context("frame file writing")?;
-                bytes_on_disk.fetch_add(
-                    file.metadata().context("frame size")?.len(),
-                    Ordering::Relaxed,
-                );
-                rename(
-                    &temp_file_path,
+            while let Ok(QueuedFrame {
+                state,
+                contents,
+                diagnostics: frame_diagnostics,
+                mut metadata,
+                state_bytes,
+            }) = store_rx.recv()
+            {
+                let start = Instant::now();
+                let frame = encode_frame(&state, contents, codec)?;
+                let file_bytes = write_frame(
                     frame_path(&cache_dir, available_frames.load(Ordering::Relaxed)),
-                )
-                .context("frame file renaming")?;
+                    &frame,
+                )?;
+                bytes_on_disk.fetch_add(file_bytes, Ordering::Relaxed);
+                thread_statistics
+                    .bytes_written
+                    .fetch_add(file_bytes, Ordering::Relaxed);
+                thread_statistics
+                    .bytes_serialized
+                    .fetch_add(frame.serialized_bytes, Ordering::Relaxed);
+                thread_statistics
+                    .nanos_writing
+                    .fetch_add(start.elapsed().as_nanos() as u64, Ordering::Relaxed);
+
+                append_diagnostics(&cache_dir, &frame_diagnostics)
+                    .context("diagnostics appending")?;
+                diagnostics.lock().unwrap().push(frame_diagnostics);
+                metadata.bytes = file_bytes;
+                {
+                    // eviction rewrites the manifest under the same lock
+                    let mut manifest = manifest.lock().unwrap();
+                    append_manifest(&cache_dir, &metadata).context("manifest appending")?;
+                    manifest.push(metadata);
+                }
                 available_frames.fetch_add(1, Ordering::Relaxed);
+                drop(state);
+                thread_statistics
+                    .queued_states
+                    .fetch_sub(1, Ordering::Relaxed);
+                thread_statistics
+                    .queued_bytes
+                    .fetch_sub(state_bytes, Ordering::Relaxed);
                 debug!(
                     "stored frame {}",
                     available_frames.load(Ordering::Relaxed) - 1
@@ -61,11 +156,54 @@ impl StoreThread {
             }
             Ok(())
         }));
-        Self { store_tx, thread }
+        Self {
+            store_tx,
+            queue_depth,
+            statistics,
+            thread,
+        }
+    }
+
+    // The sender blocks while the queue is full, so it's used without holding the cache's lock.
+    pub fn sender(&self) -> StoreSender {
+        StoreSender {
+            store_tx: self.store_tx.clone(),
+            statistics: self.statistics.clone(),
+        }
+    }
+
+    pub fn queue_full(&self) -> bool {
+        self.statistics.queued_states.load(Ordering::Relaxed) >= self.queue_depth
+    }
+
+    pub fn queued_bytes(&self) -> u64 {
+        self.statistics.queued_bytes.load(Ordering::Relaxed)
     }
 
-    pub fn store(&self, state: State) -> Result<()> {
-        Ok(self.store_tx.send(state)?)
+    pub fn task(&self) -> Task {
+        let bytes_written = self.statistics.bytes_written.load(Ordering::Relaxed);
+        let bytes_serialized = self.statistics.bytes_serialized.load(Ordering::Relaxed);
+        let seconds_writing =
+            Duration::from_nanos(self.statistics.nanos_writing.load(Ordering::Relaxed))
+                .as_secs_f64();
+        let throughput = if seconds_writing > 0. {
+            bytes_written as f64 / seconds_writing / (1 << 20) as f64
+        } else {
+            0.
+        };
+        let compression_ratio = if bytes_written > 0 {
+            bytes_serialized as f64 / bytes_written as f64
+        } else {
+            1.
+        };
+        Task {
+            name: format!(
+                "Frames Waiting to be Stored ({throughput:.1} MB/s, {compression_ratio:.1}x Compressed)"
+            ),
+            completed_steps: self.statistics.queued_states.load(Ordering::Relaxed),
+            steps_to_completion: self.queue_depth,
+            sub_tasks: Vec::new(),
+        }
     }
 
     pub fn check(&mut self) -> Result<()> {
@@ -90,7 +228,7 @@ impl Drop for StoreThread {
         let Some(thread) = self.thread.take() else {
             return;
         };
-        self.store_tx = channel().0;
+        self.store_tx = sync_channel(0).0;
         let _ = thread.join().unwrap();
     }
 }
@@ -0,0 +1,577 @@
+// SPDX-License-Identifier: MIT
+//
+// Copyright 2025  Algebraic UG (haftungsbeschränkt)
+//
+// Use of this source code is governed by an MIT-style
+// license that can be found in the LICENSE_MIT file or at
+// https://opensource.org/licenses/MIT.
+
+use std::{
+    collections::BTreeMap,
+    env,
+    fs::{File, OpenOptions, create_dir_all, remove_dir_all, remove_file},
+    io::{Cursor, Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+    process,
+};
+
+use base64::prelude::*;
+use bincode::serialize;
+use blended_mpm_api::{FrameMetadata, T};
+use fxhash::FxHashMap;
+use nalgebra::{Matrix3, Quaternion, UnitQuaternion, Vector3};
+use serde::Serialize;
+use serde_json::{to_string, to_value, to_writer};
+use strum::IntoEnumIterator;
+
+use crate::{
+    Phase, State,
+    api::{
+        BulkData, GlobalSettings, MeshHandles, Object, ObjectSettings, ObjectSettingsCollider,
+        ObjectWithHandles, ScriptedHandles, SerializedSetup, SerializedVector, SurfaceSample,
+    },
+    simulation::{
+        kinematic::{Kinematic, ScriptedMovement},
+        particles::ParticleParameters,
+        state::{
+            columns::Column,
+            lean::{FrameContents, LeanAttribute},
+            tests::{heavy_and_light_particles, state},
+        },
+    },
+};
+
+use super::{
+    Cache,
+    frame::{
+        Codec, FrameReader, MIN_FRAME_BYTES, encode_frame, is_stale_temp_file, temp_path,
+        write_frame,
+    },
+    frame_path,
+    invalidation::first_invalid_frame,
+    loaded_frames::{CAPACITY, LoadedFrames},
+    lock::{LockInfo, check_takeover, hostname},
+    manifest::{append_manifest, manifest_path},
+    setup_path,
+};
+
+// removed again when dropped
+struct TempDir(PathBuf);
+
+impl TempDir {
+    fn new(name: &str) -> Self {
+        let path = env::temp_dir().join(format!("blended_mpm_{name}_{}", process::id()));
+        let _ = remove_dir_all(&path);
+        create_dir_all(&path).unwrap();
+        Self(path)
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = remove_dir_all(&self.0);
+    }
+}
+
+fn test_state() -> State {
+    state(heavy_and_light_particles(Vector3::new(1., 2., 3.)))
+}
+
+#[test]
+fn frames_decode_as_encoded() {
+    let state = test_state();
+    let layouts = [
+        FrameContents::Checkpoint,
+        FrameContents::Lean(vec![LeanAttribute::Velocities, LeanAttribute::Grids]),
+        FrameContents::Lean(Vec::new()),
+    ];
+    for codec in [Codec::None, Codec::Lz4, Codec::Zstd] {
+        for contents in &layouts {
+            let frame = encode_frame(&state, contents.clone(), codec).unwrap();
+            let mut reader = FrameReader::new(Cursor::new(frame.bytes)).unwrap();
+            assert_eq!(reader.contents(), contents);
+            reader.verify().unwrap();
+
+            let decoded = reader.read_state(Column::iter()).unwrap();
+            for column in Column::iter().filter(|column| column.is_stored(contents)) {
+                assert_eq!(
+                    decoded.column_bytes(column).unwrap(),
+                    state.column_bytes(column).unwrap(),
+                    "{codec:?} {contents:?} {column:?}"
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn evicted_frames_only_decode_the_header() {
+    let frame = encode_frame(&test_state(), FrameContents::Evicted, Codec::Lz4).unwrap();
+    let mut reader = FrameReader::new(Cursor::new(frame.bytes)).unwrap();
+    assert_eq!(reader.contents(), &FrameContents::Evicted);
+    reader.verify().unwrap();
+    assert!(reader.read_state(Column::iter()).is_err());
+}
+
+#[test]
+fn baseline_frames_read_as_checkpoints() {
+    // the layout of the first version of the addon
+    #[derive(Clone, Serialize)]
+    enum BaselineParticleParameters {
+        Solid { mu: T, lambda: T, viscosity: T },
+    }
+    #[derive(Serialize)]
+    enum BaselineObjectIndex {
+        Solid(usize),
+        _Fluid(usize),
+        Collider(usize),
+    }
+
+    let positions: Vec<Vector3<T>> = vec![Vector3::new(0.1, 0.2, 0.3), Vector3::new(0.4, 0.5, 0.6)];
+    let velocities: Vec<Vector3<T>> = vec![Vector3::new(1., 0., 0.), Vector3::new(0., -1., 0.)];
+    let surface_samples = vec![
+        SurfaceSample {
+            position: Vector3::new(-1., 0., 0.),
+            normal: Vector3::z(),
+        },
+        SurfaceSample {
+            position: Vector3::new(1., 0., 0.),
+            normal: Vector3::z(),
+        },
+    ];
+    let kinematic = Kinematic {
+        position: Vector3::new(0., 0., -1.),
+        orientation: UnitQuaternion::identity(),
+        linear_velocity: Vector3::zeros(),
+        angular_velocity: Vector3::zeros(),
+    };
+    let mut bytes = serialize(&(
+        1.5f64,
+        Phase::Sort,
+        BTreeMap::from([
+            ("solid".to_string(), BaselineObjectIndex::Solid(0)),
+            ("collider".to_string(), BaselineObjectIndex::Collider(0)),
+        ]),
+        (
+            vec![1usize, 0],
+            vec![1usize, 0],
+            vec![
+                BaselineParticleParameters::Solid {
+                    mu: 1.,
+                    lambda: 2.,
+                    viscosity: 0.,
+                };
+                2
+            ],
+            vec![1. as T, 2.],
+            vec![1e-3 as T; 2],
+            &positions,
+            vec![Matrix3::<T>::identity(); 2],
+            &velocities,
+            vec![Matrix3::<T>::zeros(); 2],
+            vec![0. as T; 2],
+            vec![FxHashMap::<usize, bool>::default(); 2],
+            vec![Matrix3::<T>::identity(); 2],
+            vec![Matrix3::<T>::zeros(); 2],
+        ),
+        vec![(vec![0usize, 1],)],
+        Vec::<(Vec<usize>,)>::new(),
+        vec![(
+            0.5 as T,
+            0.2 as T,
+            &surface_samples,
+            &kinematic,
+            true,
+            Vec::<ScriptedMovement>::new(),
+        )],
+    ))
+    .unwrap();
+    // the grids, they're scattered again
+    bytes.extend([0xff; 64]);
+
+    let mut reader = FrameReader::new(Cursor::new(bytes)).unwrap();
+    assert_eq!(reader.contents(), &FrameContents::Checkpoint);
+    reader.verify().unwrap();
+    let state = reader.read_state([Column::Positions]).unwrap();
+    assert_eq!(state.time(), 1.5);
+    for (column, expected) in [
+        (Column::Positions, serialize(&positions)),
+        (Column::Velocities, serialize(&velocities)),
+        (
+            Column::Rest,
+            serialize(&(
+                vec![
+                    ParticleParameters::Solid {
+                        mu: 1.,
+                        lambda: 2.,
+                        viscosity: 0.,
+                        drag: 0.,
+                        stiffness_damping: 0.,
+                    };
+                    2
+                ],
+                vec![Matrix3::<T>::zeros(); 2],
+                vec![None::<usize>; 2],
+                vec![Matrix3::<T>::identity(); 2],
+                vec![Matrix3::<T>::zeros(); 2],
+            )),
+        ),
+    ] {
+        assert_eq!(
+            state.column_bytes(column).unwrap(),
+            expected.unwrap(),
+            "{column:?}"
+        );
+    }
+
+    // and stored again in the current format
+    let frame = encode_frame(&state, FrameContents::Checkpoint, Codec::Lz4).unwrap();
+    let decoded = FrameReader::new(Cursor::new(frame.bytes))
+        .unwrap()
+        .read_state(Column::iter())
+        .unwrap();
+    for column in Column::iter() {
+        assert_eq!(
+            decoded.column_bytes(column).unwrap(),
+            state.column_bytes(column).unwrap(),
+            "{column:?}"
+        );
+    }
+}
+
+fn lock_info(pid: u32, hostname: String) -> LockInfo {
+    LockInfo {
+        uuid: "uuid".to_string(),
+        pid,
+        hostname,
+        started: 1,
+        heartbeat: 2,
+    }
+}
+
+// beyond the largest pid on linux and windows
+const DEAD_PID: u32 = i32::MAX as u32;
+
+#[test]
+fn lock_info_parses() {
+    let info = lock_info(process::id(), hostname());
+    assert_eq!(LockInfo::parse(&to_string(&info).unwrap()), Some(info));
+    // older versions only wrote the uuid
+    assert_eq!(
+        LockInfo::parse("d8e4c6a2-5b1f-4c3e-9a7d-0f2b8e6c4a1d"),
+        None
+    );
+    assert_eq!(LockInfo::parse(r#"{"uuid":"uuid","pid":1"#), None);
+}
+
+#[test]
+fn stale_locks_are_taken_over() {
+    let lock_path = Path::new("lock");
+    let alive = lock_info(process::id(), hostname());
+    let dead = lock_info(DEAD_PID, hostname());
+    let elsewhere = lock_info(DEAD_PID, format!("not {}", hostname()));
+    for (owner, force, takes_over) in [
+        (Some(&alive), false, false),
+        (Some(&dead), false, true),
+        (Some(&elsewhere), false, false),
+        (None, false, false),
+        (Some(&alive), true, true),
+        (Some(&elsewhere), true, true),
+        (None, true, true),
+    ] {
+        assert_eq!(
+            check_takeover(lock_path, owner, force, 10).is_ok(),
+            takes_over,
+            "{owner:?} force: {force}"
+        );
+    }
+}
+
+#[test]
+fn only_temp_files_of_dead_processes_are_stale() {
+    let own = temp_path(Path::new("frame_00001.bin"));
+    let own = own.file_name().unwrap().to_str().unwrap();
+    assert!(!is_stale_temp_file(own));
+    assert!(is_stale_temp_file(&format!("lock.{DEAD_PID}.0.tmp")));
+    assert!(is_stale_temp_file(&format!(
+        "manifest.jsonl.{DEAD_PID}.3.tmp"
+    )));
+    assert!(is_stale_temp_file("temp.bin"));
+    assert!(!is_stale_temp_file("lock.tmp"));
+    assert!(!is_stale_temp_file("frame_00001.bin"));
+}
+
+fn float32(values: &[f32]) -> SerializedVector {
+    SerializedVector {
+        dtype: "float32".to_string(),
+        data: BASE64_STANDARD.encode(
+            values
+                .iter()
+                .flat_map(|value| value.to_le_bytes())
+                .collect::<Vec<_>>(),
+        ),
+    }
+}
+
+// A collider per animation, each scripted frame gives the height of one.
+fn serialized_setup(air_drag: T, animations: &[Vec<f32>]) -> SerializedSetup {
+    let mut serialized_vectors = BTreeMap::new();
+    let objects = animations
+        .iter()
+        .enumerate()
+        .map(|(i, animation)| {
+            let mut insert = |name: &str, vector: SerializedVector| {
+                let handle = format!("{name} {i}");
+                serialized_vectors.insert(handle.clone(), vector);
+                handle
+            };
+            let mesh_handles = MeshHandles {
+                vertices: insert("vertices", float32(&[0., 0., 0., 1., 0., 0., 0., 1., 0.])),
+                triangles: insert(
+                    "triangles",
+                    SerializedVector {
+                        dtype: "int32".to_string(),
+                        data: BASE64_STANDARD.encode(
+                            [0i32, 1, 2]
+                                .iter()
+                                .flat_map(|index| index.to_le_bytes())
+                                .collect::<Vec<_>>(),
+                        ),
+                    },
+                ),
+                triangle_normals: insert("triangle normals", float32(&[0., 0., 1.])),
+            };
+            let scripted_handles = ScriptedHandles {
+                scripted_positions: insert(
+                    "scripted positions",
+                    float32(
+                        &animation
+                            .iter()
+                            .flat_map(|height| [0., 0., *height])
+                            .collect::<Vec<_>>(),
+                    ),
+                ),
+                scripted_orientations: insert(
+                    "scripted orientations",
+                    float32(
+                        &animation
+                            .iter()
+                            .flat_map(|_| [0., 0., 0., 1.])
+                            .collect::<Vec<_>>(),
+                    ),
+                ),
+            };
+            ObjectWithHandles {
+                object: Object {
+                    name: format!("collider {i}"),
+                    scale: Vector3::new(1., 1., 1.),
+                    position: Vector3::zeros(),
+                    orientation: Quaternion::identity(),
+                    linear_velocity: Vector3::zeros(),
+                    angular_velocity: Vector3::zeros(),
+                    settings: ObjectSettings::Collider(ObjectSettingsCollider {
+                        sticky_factor: 0.,
+                        friction_factor: 0.5,
+                        static_friction_factor: 0.,
+                        restitution: 0.,
+                        adhesion_velocity: 0.,
+                    }),
+                },
+                mesh_handles,
+                scripted_handles,
+            }
+        })
+        .collect();
+    SerializedSetup {
+        settings: GlobalSettings {
+            grid_node_size: 0.1,
+            particle_size: 0.05,
+            frames_per_second: 24,
+            gravity: Vector3::new(0., 0., -9.81),
+            contact_friction_factor: 0.,
+            air_drag,
+            seed: 0,
+            deterministic: false,
+        },
+        objects,
+        bulk_data: BulkData { serialized_vectors },
+    }
+}
+
+#[test]
+fn changes_invalidate_from_the_first_affected_frame() {
+    let temp_dir = TempDir::new("invalidation");
+    let new = serialized_setup(0., &[vec![0., 1., 2., 3.]]);
+    assert_eq!(first_invalid_frame(&temp_dir.0, &new).unwrap(), Some(0));
+
+    let changed_at = |frame: usize| {
+        let mut animation = vec![0., 1., 2., 3.];
+        animation[frame] += 1.;
+        animation
+    };
+    for (change, animations, air_drag, expected) in [
+        ("no change", vec![vec![0., 1., 2., 3.]], 0., None),
+        ("settings", vec![vec![0., 1., 2., 3.]], 0.1, Some(0)),
+        ("scripted frame 2", vec![changed_at(2)], 0., Some(1)),
+        ("scripted frame 1", vec![changed_at(1)], 0., Some(0)),
+        ("scripted frame 0", vec![changed_at(0)], 0., Some(0)),
+        (
+            "longer animation",
+            vec![vec![0., 1., 2., 3., 4.]],
+            0.,
+            Some(3),
+        ),
+        (
+            "added object",
+            vec![vec![0., 1., 2., 3.], vec![0., 1., 2., 3.]],
+            0.,
+            Some(0),
+        ),
+        ("removed object", Vec::new(), 0., Some(0)),
+    ] {
+        let stored = serialized_setup(0., &[vec![0., 1., 2., 3.]]);
+        to_writer(File::create(setup_path(&temp_dir.0)).unwrap(), &stored).unwrap();
+        let new = serialized_setup(air_drag, &animations);
+        assert_eq!(
+            first_invalid_frame(&temp_dir.0, &new).unwrap(),
+            expected,
+            "{change}"
+        );
+    }
+}
+
+// Stores the frames like the store thread would and loads the cache again.
+fn cache_with_frames(temp_dir: &TempDir, frames: usize) -> Cache {
+    let setup = to_value(serialized_setup(0., &[vec![0.]])).unwrap();
+    drop(Cache::new("uuid".to_string(), setup, temp_dir.0.clone(), u64::MAX).unwrap());
+    let frame = encode_frame(&test_state(), FrameContents::Checkpoint, Codec::Lz4).unwrap();
+    for i in 0..frames {
+        let bytes = write_frame(frame_path(&temp_dir.0, i), &frame).unwrap();
+        let metadata = FrameMetadata {
+            bytes,
+            ..Default::default()
+        };
+        append_manifest(&temp_dir.0, &metadata).unwrap();
+    }
+    Cache::load("uuid".to_string(), temp_dir.0.clone(), u64::MAX, false).unwrap()
+}
+
+#[test]
+fn verify_finds_and_repair_drops_corrupt_frames() {
+    let temp_dir = TempDir::new("verify");
+    let cache = cache_with_frames(&temp_dir, 3);
+    assert_eq!(cache.verify(false).unwrap(), None);
+
+    // the last byte belongs to the last column
+    let mut file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(frame_path(&temp_dir.0, 1))
+        .unwrap();
+    file.seek(SeekFrom::End(-1)).unwrap();
+    let mut byte = [0];
+    file.read_exact(&mut byte).unwrap();
+    file.seek(SeekFrom::End(-1)).unwrap();
+    file.write_all(&[!byte[0]]).unwrap();
+    drop(file);
+
+    assert_eq!(cache.verify(false).unwrap(), Some(1));
+    assert_eq!(cache.available_frames(), 3);
+    assert!(frame_path(&temp_dir.0, 2).is_file());
+
+    assert_eq!(cache.verify(true).unwrap(), Some(1));
+    assert_eq!(cache.available_frames(), 1);
+    assert_eq!(cache.manifest().len(), 1);
+    assert!(!frame_path(&temp_dir.0, 1).exists());
+    assert_eq!(cache.verify(false).unwrap(), None);
+}
+
+#[test]
+fn check_scans_the_frames_only_without_manifest() {
+    let temp_dir = TempDir::new("check");
+    let cache = cache_with_frames(&temp_dir, 3);
+    cache.check().unwrap();
+
+    // listed in the manifest, the frames on disk aren't looked at
+    remove_file(frame_path(&temp_dir.0, 1)).unwrap();
+    cache.check().unwrap();
+
+    remove_file(manifest_path(&temp_dir.0)).unwrap();
+    assert!(cache.check().is_err());
+}
+
+#[test]
+fn cut_off_frames_are_dropped_on_load() {
+    let temp_dir = TempDir::new("cut_off");
+    drop(cache_with_frames(&temp_dir, 3));
+    OpenOptions::new()
+        .write(true)
+        .open(frame_path(&temp_dir.0, 1))
+        .unwrap()
+        .set_len(MIN_FRAME_BYTES - 1)
+        .unwrap();
+
+    let cache = Cache::load("uuid".to_string(), temp_dir.0.clone(), u64::MAX, false).unwrap();
+    assert_eq!(cache.available_frames(), 1);
+    assert_eq!(cache.manifest().len(), 1);
+    assert!(!frame_path(&temp_dir.0, 1).exists());
+    assert!(!frame_path(&temp_dir.0, 2).exists());
+}
+
+#[test]
+fn frames_cut_off_in_a_column_are_dropped_on_load() {
+    let temp_dir = TempDir::new("cut_off_column");
+    drop(cache_with_frames(&temp_dir, 4));
+    // evicted before a crash, the manifest still lists the checkpoint
+    let evicted = encode_frame(&test_state(), FrameContents::Evicted, Codec::Lz4).unwrap();
+    write_frame(frame_path(&temp_dir.0, 1), &evicted).unwrap();
+    let file = OpenOptions::new()
+        .write(true)
+        .open(frame_path(&temp_dir.0, 2))
+        .unwrap();
+    let bytes = file.metadata().unwrap().len();
+    file.set_len(bytes - 10).unwrap();
+    drop(file);
+
+    let cache = Cache::load("uuid".to_string(), temp_dir.0.clone(), u64::MAX, false).unwrap();
+    assert_eq!(cache.available_frames(), 2);
+    let manifest = cache.manifest();
+    assert_eq!(manifest.len(), 2);
+    assert!(manifest[1].evicted);
+    assert!(!frame_path(&temp_dir.0, 2).exists());
+    assert!(!frame_path(&temp_dir.0, 3).exists());
+}
+
+// Whether the frame had to be read.
+fn loads(loaded_frames: &mut LoadedFrames, frame: usize, columns: &[Column]) -> bool {
+    let mut loaded = false;
+    loaded_frames
+        .get(frame, columns, |_| {
+            loaded = true;
+            Ok((test_state(), FrameContents::Checkpoint))
+        })
+        .unwrap();
+    loaded
+}
+
+#[test]
+fn least_recently_used_frames_are_read_again() {
+    let mut loaded_frames = LoadedFrames::default();
+    let positions = [Column::Objects, Column::Positions];
+    for frame in 0..CAPACITY {
+        assert!(loads(&mut loaded_frames, frame, &positions));
+    }
+    for frame in 0..CAPACITY {
+        assert!(!loads(&mut loaded_frames, frame, &positions));
+    }
+
+    assert!(!loads(&mut loaded_frames, 0, &positions));
+    // frame 1 is the least recently used now
+    assert!(loads(&mut loaded_frames, CAPACITY, &positions));
+    assert!(!loads(&mut loaded_frames, 0, &positions));
+    assert!(loads(&mut loaded_frames, 1, &positions));
+
+    // only columns that weren't read yet are a reason to read again
+    assert!(!loads(&mut loaded_frames, 0, &[Column::Objects]));
+    assert!(loads(&mut loaded_frames, 0, &[Column::Velocities]));
+}
@@ -0,0 +1,47 @@
+// SPDX-License-Identifier: MIT
+//
+// Copyright 2025  Algebraic UG (haftungsbeschränkt)
+//
+// Use of this source code is governed by an MIT-style
+// license that can be found in the LICENSE_MIT file or at
+// https://opensource.org/licenses/MIT.
+
+use std::sync::atomic::Ordering;
+
+use anyhow::Result;
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+use tracing::{info, warn};
+
+use super::{Cache, frame::FrameReader, frame_path};
+
+impl Cache {
+    // Scans all frames and returns the first bad one.
+    // `repair` truncates the cache there s.t. computing resumes from the last good checkpoint.
+    pub fn verify(&self, repair: bool) -> Result<Option<usize>> {
+        let available_frames = self.available_frames.load(Ordering::Relaxed);
+        let cache_dir = self.cache_lock.cache_dir();
+        info!(available_frames, "verifying frames");
+
+        let first_bad_frame = (0..available_frames)
+            .into_par_iter()
+            .filter(|frame| {
+                let result = FrameReader::open(frame_path(cache_dir, *frame))
+                    .and_then(|mut reader| reader.verify());
+                if let Err(e) = &result {
+                    warn!(frame, "bad frame: {e:?}");
+                }
+                result.is_err()
+            })
+            .min();
+
+        match first_bad_frame {
+            Some(frame) if repair => {
+                warn!(frame, "truncating cache at the first bad frame");
+                self.drop_frames(frame)?;
+            }
+            Some(frame) => warn!(frame, "cache has bad frames"),
+            None => info!("all frames are intact"),
+        }
+        Ok(first_bad_frame)
+    }
+}
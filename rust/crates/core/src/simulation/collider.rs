@@ -13,7 +13,7 @@ use std::{
 
 use crate::{
     api::{GlobalSettings, Mesh, ObjectSettingsCollider, ScriptedFrame, SurfaceSample},
-    math::NORMALIZATION_EPS,
+    math::{Aabb, NORMALIZATION_EPS},
     report::Report,
 };
 use anyhow::{Context, Result};
@@ -28,11 +28,20 @@ use super::kinematic::{Kinematic, ScriptedMovement};
 pub struct Collider {
     pub sticky_factor: T,
     pub friction_factor: T,
+    pub static_friction_factor: T,
+    pub restitution: T,
+    pub adhesion_velocity: T,
 
     pub surface_samples: Vec<SurfaceSample>,
 
+    // local bounding sphere of the surface samples
+    pub bounding_center: Vector3<T>,
+    pub bounding_radius: T,
+
     pub kinematic: Kinematic,
     pub has_moved: bool,
+    // no particle was close in the last step
+    pub culled: bool,
 
     // TODO: this doesn't need to be stored in each state
     pub scripted_movements: Vec<ScriptedMovement>,
@@ -49,6 +58,46 @@ pub struct ColliderConstruction<'a> {
     pub scripted_frames: Vec<ScriptedFrame>,
 }
 
+// Scripted frame `i` is reached at the time of frame `i`.
+pub fn scripted_movements(
+    scripted_frames: &[ScriptedFrame],
+    frames_per_second: u32,
+) -> Result<Vec<ScriptedMovement>> {
+    let seconds_per_frame = 1. / (frames_per_second as T);
+    scripted_frames
+        .iter()
+        .enumerate()
+        .map(
+            |(
+                frame,
+                ScriptedFrame {
+                    position,
+                    orientation,
+                },
+            )| {
+                Ok(ScriptedMovement {
+                    time: seconds_per_frame * frame as T,
+                    position: *position,
+                    orientation: UnitQuaternion::try_new(*orientation, NORMALIZATION_EPS)
+                        .context("Orientation not normalized")?,
+                })
+            },
+        )
+        .collect::<Result<Vec<_>>>()
+        .context("Scripted frames parsing")
+}
+
+// Centered in the bounding box of the samples, in the collider's local coordinates.
+pub fn bounding_sphere(surface_samples: &[SurfaceSample]) -> (Vector3<T>, T) {
+    let bounding_box = Aabb::new(surface_samples.iter().map(|sample| sample.position));
+    let bounding_center = (bounding_box.min + bounding_box.max) / 2.;
+    let bounding_radius = surface_samples
+        .iter()
+        .map(|sample| (sample.position - bounding_center).norm())
+        .fold(0., T::max);
+    (bounding_center, bounding_radius)
+}
+
 impl Collider {
     pub fn new(
         ColliderConstruction {
@@ -66,6 +115,9 @@ impl Collider {
                 ObjectSettingsCollider {
                     sticky_factor,
                     friction_factor,
+                    static_friction_factor,
+                    restitution,
+                    adhesion_velocity,
                 },
             mesh,
             scripted_frames,
@@ -79,43 +131,30 @@ impl Collider {
             steps_to_completion: NonZero::new(2).unwrap(),
         });
 
-        let seconds_per_frame = 1. / (*frames_per_second as T);
-        let scripted_movements = scripted_frames
-            .into_iter()
-            .enumerate()
-            .map(
-                |(
-                    frame,
-                    ScriptedFrame {
-                        position,
-                        orientation,
-                    },
-                )| {
-                    Ok(ScriptedMovement {
-                        time: seconds_per_frame * frame as T,
-                        position,
-                        orientation: UnitQuaternion::try_new(orientation, NORMALIZATION_EPS)
-                            .context("Orientation not normalized")?,
-                    })
-                },
-            )
-            .collect::<Result<Vec<_>>>()
-            .context("Scripted frames parsing")?;
+        let scripted_movements = scripted_movements(&scripted_frames, *frames_per_second)?;
         report.step();
 
         let surface_samples = mesh.sample_surface(run, *grid_node_size / 2.)?;
+        let (bounding_center, bounding_radius) = bounding_sphere(&surface_samples);
         report.step();
 
         Ok(Self {
             sticky_factor,
             friction_factor,
+            static_friction_factor,
+            restitution,
+            adhesion_velocity,
             surface_samples,
+            bounding_center,
+            bounding_radius,
             kinematic,
             has_moved: true,
+            culled: false,
             scripted_movements,
         })
     }
 
+    // The normal is expected to point towards the side the material is on.
     pub fn conform_velocity(
         &self,
         position: Vector3<T>,
@@ -129,18 +168,48 @@ impl Collider {
         let normal_part = normal.dot(&relative_velocity);
         let normal_velocity = normal * normal_part;
         let tangent_velocity = relative_velocity - normal_velocity;
-        let tangent_part = tangent_velocity.norm();
 
         point_velocity
             + if normal_part < 0. {
-                if tangent_part == 0. {
-                    Vector3::zeros()
-                } else {
-                    tangent_velocity
-                        * (1. + self.friction_factor * normal_part / tangent_part).max(0.)
-                }
+                let bounce_part = -self.restitution * normal_part;
+                normal * bounce_part + self.friction(tangent_velocity, bounce_part - normal_part)
+            } else if normal_part < self.adhesion_velocity {
+                tangent_velocity
             } else {
                 tangent_velocity + normal * (1. - self.sticky_factor) * normal_part
             }
     }
+
+    // Same as above, but the material has already penetrated the collider by some depth.
+    // The normal part is overwritten s.t. the penetration is resolved in one time step.
+    pub fn penalty_velocity(
+        &self,
+        position: Vector3<T>,
+        velocity: Vector3<T>,
+        normal: Vector3<T>,
+        penetration: T,
+        time_step: T,
+    ) -> Vector3<T> {
+        let point_velocity = self.kinematic.point_velocity_from_world(position);
+
+        let relative_velocity = velocity - point_velocity;
+
+        let normal_part = normal.dot(&relative_velocity);
+        let tangent_velocity = relative_velocity - normal * normal_part;
+
+        let corrected_part = (penetration / time_step).max(-self.restitution * normal_part);
+
+        point_velocity
+            + normal * corrected_part
+            + self.friction(tangent_velocity, (corrected_part - normal_part).abs())
+    }
+
+    // Coulomb friction with the change in normal velocity as impulse.
+    fn friction(&self, tangent_velocity: Vector3<T>, normal_impulse: T) -> Vector3<T> {
+        let tangent_part = tangent_velocity.norm();
+        if tangent_part <= self.static_friction_factor * normal_impulse {
+            return Vector3::zeros();
+        }
+        tangent_velocity * (1. - self.friction_factor * normal_impulse / tangent_part).max(0.)
+    }
 }
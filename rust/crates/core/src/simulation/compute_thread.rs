@@ -17,16 +17,17 @@ use std::{
 
 use anyhow::{Context, Result};
 use blended_mpm_api::Task;
+use rayon::ThreadPoolBuilder;
 use strum::IntoEnumIterator;
 use tracing::{debug, info};
 
 use crate::{
     State,
     report::{Report, ReportInfo},
-    simulation::state::{Phase, PhaseInput},
+    simulation::state::{Phase, PhaseInput, lean::FrameContents},
 };
 
-use super::cache::Cache;
+use super::cache::{Cache, FrameStatistics};
 
 pub struct ComputeThread {
     run: Arc<AtomicBool>,
@@ -41,8 +42,23 @@ impl ComputeThread {
         mut phase_input: PhaseInput,
         number_of_frames: NonZero<usize>,
         mut next_frame: usize,
+        number_of_threads: usize,
+        low_priority: bool,
     ) -> Result<Self> {
-        info!("starting compute thread");
+        info!(number_of_threads, low_priority, "starting compute thread");
+
+        // Every phase, and the sampling of the objects' meshes, runs in this pool.
+        // Simulations that compute at the same time don't compete for all cores.
+        let pool = ThreadPoolBuilder::new()
+            .num_threads(number_of_threads)
+            .thread_name(|i| format!("blended_mpm_compute_{i}"))
+            .start_handler(move |_| {
+                if low_priority {
+                    lower_thread_priority();
+                }
+            })
+            .build()
+            .context("Thread pool creation")?;
 
         let run = Arc::new(AtomicBool::new(true));
         let report = Report::new(ReportInfo {
@@ -55,62 +71,99 @@ impl ComputeThread {
             let run = run.clone();
             let frame_report = report.clone();
             Some(spawn(move || -> Result<()> {
-                let mut current_state = if next_frame == 0 {
-                    let state = State::new(run.clone(), frame_report.clone(), &cache.setup)?;
-                    cache.store_frame(state.clone())?;
-                    frame_report.step();
-                    next_frame += 1;
-                    state
-                } else {
-                    cache.fetch_frame(next_frame - 1)?
-                };
-
-                while next_frame < number_of_frames.get() {
-                    let step_report = frame_report.new_sub(ReportInfo {
-                        name: "Simulation Milliseconds to Next Frame".to_string(),
+                if low_priority {
+                    lower_thread_priority();
+                }
+                pool.install(|| -> Result<()> {
+                    let memory_report = frame_report.new_sub(ReportInfo {
+                        name: "Memory Usage (Megabytes)".to_string(),
                         completed_steps: 0,
                         steps_to_completion: NonZero::new(
-                            ((seconds_per_frame * 1000.) as usize).max(1),
+                            ((cache.max_bytes_in_memory() >> 20) as usize).max(1),
                         )
                         .unwrap(),
                     });
-
-                    let next_stored_frame_time = next_frame as f64 * seconds_per_frame;
-                    while current_state.time() < next_stored_frame_time {
-                        let phase_report = step_report.new_sub(ReportInfo {
-                            name: "Phases".to_string(),
+                    let check_memory = |state: &State| -> Result<()> {
+                        let bytes_in_memory = cache.check_memory(&state.memory_usage())?;
+                        memory_report.set_completed((bytes_in_memory >> 20) as usize);
+                        Ok(())
+                    };
+
+                    let frame_policy = cache.frame_policy();
+                    let mut current_state = if next_frame == 0 {
+                        let statistics = FrameStatistics::start();
+                        let state = State::new(run.clone(), frame_report.clone(), &cache.setup)?;
+                        check_memory(&state)?;
+                        cache.store_frame(
+                            &state,
+                            FrameContents::Checkpoint,
+                            statistics,
+                            &frame_report,
+                        )?;
+                        frame_report.step();
+                        next_frame += 1;
+                        state
+                    } else {
+                        cache.fetch_frame(next_frame - 1)?
+                    };
+
+                    while next_frame < number_of_frames.get() {
+                        let mut statistics = FrameStatistics::start();
+                        let step_report = frame_report.new_sub(ReportInfo {
+                            name: "Simulation Milliseconds to Next Frame".to_string(),
                             completed_steps: 0,
-                            steps_to_completion: NonZero::new(Phase::iter().count()).unwrap(),
+                            steps_to_completion: NonZero::new(
+                                ((seconds_per_frame * 1000.) as usize).max(1),
+                            )
+                            .unwrap(),
                         });
-                        loop {
-                            if !run.load(Ordering::Relaxed) {
-                                return Ok(());
-                            }
 
-                            current_state = current_state.next(&mut phase_input)?;
-                            phase_report.step();
-
-                            if !run.load(Ordering::Relaxed) {
-                                return Ok(());
+                        let next_stored_frame_time = next_frame as f64 * seconds_per_frame;
+                        while current_state.time() < next_stored_frame_time {
+                            let phase_report = step_report.new_sub(ReportInfo {
+                                name: "Phases".to_string(),
+                                completed_steps: 0,
+                                steps_to_completion: NonZero::new(Phase::iter().count()).unwrap(),
+                            });
+                            let substep_start_time = current_state.time();
+                            loop {
+                                if !run.load(Ordering::Relaxed) {
+                                    return Ok(());
+                                }
+
+                                current_state = current_state.next(&mut phase_input)?;
+                                phase_report.step();
+
+                                if !run.load(Ordering::Relaxed) {
+                                    return Ok(());
+                                }
+
+                                if current_state.phase() == Phase::default() {
+                                    break;
+                                }
                             }
+                            // the grids of the substep are still allocated
+                            check_memory(&current_state)?;
 
-                            if current_state.phase() == Phase::default() {
-                                break;
-                            }
+                            statistics.add_substep(current_state.time() - substep_start_time);
+                            step_report.set_completed(
+                                ((current_state.time() % seconds_per_frame) * 1000.) as usize,
+                            );
                         }
-
-                        step_report.set_completed(
-                            ((current_state.time() % seconds_per_frame) * 1000.) as usize,
-                        );
+                        frame_report.step();
+
+                        cache.store_frame(
+                            &current_state,
+                            frame_policy.contents(next_frame, number_of_frames.get()),
+                            statistics,
+                            &frame_report,
+                        )?;
+                        debug!("computed frame {} of {}", next_frame, number_of_frames);
+                        next_frame += 1;
                     }
-                    frame_report.step();
 
-                    cache.store_frame(current_state.clone())?;
-                    debug!("computed frame {} of {}", next_frame, number_of_frames);
-                    next_frame += 1;
-                }
-
-                Ok(())
+                    Ok(())
+                })
             }))
         };
 
@@ -149,3 +202,20 @@ impl Drop for ComputeThread {
         let _ = thread.join().unwrap();
     }
 }
+
+// Keeps Blender's UI responsive while computing, only affects the calling thread.
+fn lower_thread_priority() {
+    #[cfg(target_os = "linux")]
+    // SAFETY: plain syscall, on Linux the niceness is per thread
+    unsafe {
+        libc::setpriority(libc::PRIO_PROCESS, libc::gettid() as libc::id_t, 10);
+    }
+    #[cfg(windows)]
+    // SAFETY: the pseudo handle of the current thread is always valid
+    unsafe {
+        use windows_sys::Win32::System::Threading::{
+            GetCurrentThread, SetThreadPriority, THREAD_PRIORITY_BELOW_NORMAL,
+        };
+        SetThreadPriority(GetCurrentThread(), THREAD_PRIORITY_BELOW_NORMAL);
+    }
+}
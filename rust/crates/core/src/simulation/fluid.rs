@@ -19,6 +19,7 @@ use tracing::info;
 use crate::{
     Report,
     api::{GlobalSettings, Mesh, ObjectSettingsFluid},
+    math::random::random_stream,
     simulation::{
         particles::{ParticleParameters, Particles},
         state::profile,
@@ -30,6 +31,7 @@ use super::kinematic::Kinematic;
 #[derive(Clone, Serialize, Deserialize)]
 pub struct Fluid {
     pub particles: Vec<usize>,
+    pub velocity_field: Option<usize>,
 }
 
 pub struct FluidConstruction<'a> {
@@ -41,6 +43,7 @@ pub struct FluidConstruction<'a> {
     pub object_settings: ObjectSettingsFluid,
     pub mesh: &'a Mesh,
     pub particles: &'a mut Particles,
+    pub velocity_field: Option<usize>,
 }
 
 impl Fluid {
@@ -49,7 +52,12 @@ impl Fluid {
             name,
             run,
             report,
-            settings: GlobalSettings { particle_size, .. },
+            settings:
+                GlobalSettings {
+                    particle_size,
+                    seed,
+                    ..
+                },
             kinematic:
                 Kinematic {
                     position,
@@ -65,9 +73,13 @@ impl Fluid {
                     bulk_modulus,
                     dilation,
                     randomness,
+                    drag,
+                    stiffness_damping,
+                    separate_velocity_field: _,
                 },
             mesh,
             particles,
+            velocity_field,
         }: FluidConstruction,
     ) -> Result<Self> {
         info!("fuild object");
@@ -96,6 +108,7 @@ impl Fluid {
             report.clone(),
             *particle_size * dilation,
             randomness,
+            random_stream(*seed, name),
         )?;
         let first_idx = particles.sort_map.len();
         report.step();
@@ -105,6 +118,8 @@ impl Fluid {
             let Particles {
                 sort_map,
                 reverse_sort_map,
+                sort_keys: _,
+                stencil_weights: _,
                 parameters,
                 masses,
                 initial_volumes,
@@ -114,6 +129,7 @@ impl Fluid {
                 velocity_gradients,
                 elastic_energies,
                 collider_insides,
+                velocity_fields,
                 trial_position_gradients: _,
                 action_matrices: _,
             } = particles;
@@ -132,6 +148,8 @@ impl Fluid {
                     exponent,
                     bulk_modulus,
                     viscosity,
+                    drag,
+                    stiffness_damping,
                 },
             );
             masses.resize(n, mass);
@@ -140,6 +158,7 @@ impl Fluid {
             velocity_gradients.resize(n, velocity_gradient);
             elastic_energies.resize(n, elastic_energy);
             collider_insides.resize(n, Default::default());
+            velocity_fields.resize(n, velocity_field);
 
             positions.extend(
                 samples
@@ -155,6 +174,7 @@ impl Fluid {
 
         Ok(Self {
             particles: (first_idx..first_idx + samples.len()).collect(),
+            velocity_field,
         })
     }
 }
@@ -0,0 +1,152 @@
+// SPDX-License-Identifier: MIT
+//
+// Copyright 2025  Algebraic UG (haftungsbeschränkt)
+//
+// Use of this source code is governed by an MIT-style
+// license that can be found in the LICENSE_MIT file or at
+// https://opensource.org/licenses/MIT.
+
+use std::cell::Cell;
+
+use fxhash::FxHashMap;
+use nalgebra::Vector3;
+use rayon::iter::{IndexedParallelIterator, IntoParallelIterator, ParallelIterator};
+use serde::{Deserialize, Serialize};
+
+use crate::simulation::memory::HeapBytes;
+
+pub const BLOCK_BITS: i32 = 2;
+pub const BLOCK_SIZE: i32 = 1 << BLOCK_BITS;
+pub const BLOCK_VOLUME: usize = (BLOCK_SIZE * BLOCK_SIZE * BLOCK_SIZE) as usize;
+
+// Split a node into the coordinate of its block and the offset inside the block.
+fn split(grid_idx: Vector3<i32>) -> (Vector3<i32>, usize) {
+    let block = grid_idx.map(|i| i >> BLOCK_BITS);
+    let local = grid_idx.map(|i| (i & (BLOCK_SIZE - 1)) as usize);
+    (
+        block,
+        (local.x * BLOCK_SIZE as usize + local.y) * BLOCK_SIZE as usize + local.z,
+    )
+}
+
+pub fn block_of(grid_idx: Vector3<i32>) -> Vector3<i32> {
+    split(grid_idx).0
+}
+
+// A quadratic stencil starting in a block only reaches into the next block along each axis.
+// So particles in different blocks of the same color never share a node.
+pub fn block_color(block: Vector3<i32>) -> usize {
+    (block.x & 1 | (block.y & 1) << 1 | (block.z & 1) << 2) as usize
+}
+
+pub const BLOCK_COLORS: usize = 8;
+
+// Nodes are allocated in dense blocks, s.t. the 27 nodes a particle touches
+// are found with few hash lookups and lie close together in memory.
+// The index of a node is the index of its block times the block volume plus its offset,
+// any data vector indexed by it has to be sized with `len`.
+#[derive(Default, Clone, Serialize, Deserialize)]
+pub struct BlockMap {
+    blocks: FxHashMap<Vector3<i32>, usize>,
+    coordinates: Vec<Vector3<i32>>,
+}
+
+impl HeapBytes for BlockMap {
+    fn heap_bytes(&self) -> u64 {
+        self.blocks.heap_bytes() + self.coordinates.heap_bytes()
+    }
+}
+
+impl BlockMap {
+    // number of nodes in all allocated blocks, including unused ones
+    pub fn len(&self) -> usize {
+        self.coordinates.len() * BLOCK_VOLUME
+    }
+
+    pub fn get(&self, grid_idx: &Vector3<i32>) -> Option<usize> {
+        let (block, local) = split(*grid_idx);
+        self.blocks
+            .get(&block)
+            .map(|block_idx| block_idx * BLOCK_VOLUME + local)
+    }
+
+    pub fn contains_key(&self, grid_idx: &Vector3<i32>) -> bool {
+        self.blocks.contains_key(&split(*grid_idx).0)
+    }
+
+    // Allocates the whole block if needed.
+    pub fn insert(&mut self, grid_idx: Vector3<i32>) {
+        self.insert_block(block_of(grid_idx));
+    }
+
+    pub fn insert_block(&mut self, block: Vector3<i32>) {
+        self.blocks.entry(block).or_insert_with(|| {
+            self.coordinates.push(block);
+            self.coordinates.len() - 1
+        });
+    }
+
+    pub fn key(&self, idx: usize) -> Vector3<i32> {
+        let block = self.coordinates[idx / BLOCK_VOLUME];
+        let local = idx % BLOCK_VOLUME;
+        let size = BLOCK_SIZE as usize;
+        block * BLOCK_SIZE
+            + Vector3::new(local / size / size, local / size % size, local % size).map(|i| i as i32)
+    }
+
+    // All nodes in index order.
+    pub fn iter(&self) -> impl Iterator<Item = (Vector3<i32>, usize)> + '_ {
+        (0..self.len()).map(|idx| (self.key(idx), idx))
+    }
+
+    // All nodes in index order, to be zipped with the data vectors.
+    pub fn par_keys(&self) -> impl IndexedParallelIterator<Item = Vector3<i32>> + '_ {
+        (0..self.len()).into_par_iter().map(|idx| self.key(idx))
+    }
+
+    // Drop blocks by their index, the remaining ones are re-indexed.
+    pub fn retain_blocks(&mut self, mut keep: impl FnMut(usize) -> bool) {
+        let mut block_idx = 0;
+        self.coordinates.retain(|_| {
+            block_idx += 1;
+            keep(block_idx - 1)
+        });
+        self.blocks = self
+            .coordinates
+            .iter()
+            .enumerate()
+            .map(|(block_idx, block)| (*block, block_idx))
+            .collect();
+    }
+
+    pub fn stencil(&self, shift: Vector3<i32>) -> Stencil<'_> {
+        Stencil {
+            map: self,
+            base: split(shift).0,
+            blocks: Default::default(),
+        }
+    }
+}
+
+// The quadratic stencil starting at `shift` touches at most 2x2x2 blocks,
+// each of them is only looked up once.
+pub struct Stencil<'a> {
+    map: &'a BlockMap,
+    base: Vector3<i32>,
+    blocks: [Cell<Option<Option<usize>>>; 8],
+}
+
+impl Stencil<'_> {
+    pub fn get(&self, grid_idx: &Vector3<i32>) -> Option<usize> {
+        let (block, local) = split(*grid_idx);
+        let offset = block - self.base;
+        debug_assert!(offset.iter().all(|i| (0..=1).contains(i)));
+        let cached = &self.blocks[(offset.x * 4 + offset.y * 2 + offset.z) as usize];
+        let block_idx = cached.get().unwrap_or_else(|| {
+            let block_idx = self.map.blocks.get(&block).copied();
+            cached.set(Some(block_idx));
+            block_idx
+        });
+        block_idx.map(|block_idx| block_idx * BLOCK_VOLUME + local)
+    }
+}
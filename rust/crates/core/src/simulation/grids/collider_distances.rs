@@ -6,14 +6,12 @@
 // license that can be found in the LICENSE_MIT file or at
 // https://opensource.org/licenses/MIT.
 
-use std::ops::{Deref, DerefMut};
-
 use blended_mpm_api::T;
 use fxhash::FxHashMap;
 use nalgebra::Vector3;
 use serde::{Deserialize, Serialize};
 
-use super::Mutex;
+use super::{BlockMap, Stencil};
 
 #[derive(Clone, Copy, Serialize, Deserialize)]
 pub struct WeightedDistance {
@@ -26,19 +24,56 @@ pub struct GridNodeColliderDistances {
     pub weighted_distances: FxHashMap<usize, WeightedDistance>,
 }
 
+// Only the scatter writes the nodes, everything else reads them without locking.
 #[derive(Default, Clone, Serialize, Deserialize)]
-pub struct GridColliderDistances(pub FxHashMap<Vector3<i32>, Mutex<GridNodeColliderDistances>>);
+pub struct GridColliderDistances {
+    pub map: BlockMap,
+    pub nodes: Vec<GridNodeColliderDistances>,
+}
+
+impl GridColliderDistances {
+    pub fn get(&self, grid_idx: &Vector3<i32>) -> Option<&GridNodeColliderDistances> {
+        self.map.get(grid_idx).map(|idx| &self.nodes[idx])
+    }
 
-impl Deref for GridColliderDistances {
-    type Target = FxHashMap<Vector3<i32>, Mutex<GridNodeColliderDistances>>;
+    pub fn contains_key(&self, grid_idx: &Vector3<i32>) -> bool {
+        self.map.contains_key(grid_idx)
+    }
+
+    pub fn extend(&mut self, grid_idxs: impl IntoIterator<Item = Vector3<i32>>) {
+        grid_idxs
+            .into_iter()
+            .for_each(|grid_idx| self.map.insert(grid_idx));
+        self.nodes.resize_with(self.map.len(), Default::default);
+    }
 
-    fn deref(&self) -> &Self::Target {
-        &self.0
+    // Only the nodes that are close to a collider.
+    pub fn iter(&self) -> impl Iterator<Item = (Vector3<i32>, &GridNodeColliderDistances)> {
+        self.map
+            .iter()
+            .map(|(grid_idx, idx)| (grid_idx, &self.nodes[idx]))
+            .filter(|(_, node)| !node.weighted_distances.is_empty())
     }
+
+    pub fn values_mut(&mut self) -> impl Iterator<Item = &mut GridNodeColliderDistances> {
+        self.nodes.iter_mut()
+    }
+
+    pub fn stencil(&self, shift: Vector3<i32>) -> ColliderDistancesStencil<'_> {
+        ColliderDistancesStencil {
+            nodes: &self.nodes,
+            stencil: self.map.stencil(shift),
+        }
+    }
+}
+
+pub struct ColliderDistancesStencil<'a> {
+    nodes: &'a [GridNodeColliderDistances],
+    stencil: Stencil<'a>,
 }
 
-impl DerefMut for GridColliderDistances {
-    fn deref_mut(&mut self) -> &mut Self::Target {
-        &mut self.0
+impl<'a> ColliderDistancesStencil<'a> {
+    pub fn get(&self, grid_idx: &Vector3<i32>) -> Option<&'a GridNodeColliderDistances> {
+        self.stencil.get(grid_idx).map(|idx| &self.nodes[idx])
     }
 }
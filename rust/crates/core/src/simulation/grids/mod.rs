@@ -6,10 +6,14 @@
 // license that can be found in the LICENSE_MIT file or at
 // https://opensource.org/licenses/MIT.
 
+mod block_map;
 mod collider_distances;
 mod momentum;
 mod mutex;
+mod shared_slice;
 
+pub use block_map::*;
 pub use collider_distances::*;
 pub use momentum::*;
 pub use mutex::*;
+pub use shared_slice::*;
@@ -6,52 +6,40 @@
 // license that can be found in the LICENSE_MIT file or at
 // https://opensource.org/licenses/MIT.
 
-use blended_mpm_api::T;
-use fxhash::FxHashMap;
+use blended_mpm_api::A;
 use nalgebra::Vector3;
-use rayon::iter::{IntoParallelRefMutIterator, ParallelIterator};
 use serde::{Deserialize, Serialize};
 
-use super::Mutex;
+use super::BlockMap;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Boundary {
     // fixed in one time step
-    pub normal: Vector3<T>,
-    pub collider_value: T,
+    pub normal: Vector3<A>,
+    pub collider_value: A,
 
     // change in implicit solving
-    pub condition_value: T,
-    pub dual_variable: T,
+    pub condition_value: A,
+    pub dual_variable: A,
 }
 
 #[derive(Default, Clone, Serialize, Deserialize)]
 pub struct GridMomentum {
-    pub map: FxHashMap<Vector3<i32>, usize>,
+    pub map: BlockMap,
 
-    pub contributors: Vec<Mutex<Vec<usize>>>,
+    pub masses: Vec<A>,
+    pub velocities: Vec<Vector3<A>>,
 
-    pub masses: Vec<T>,
-    pub velocities: Vec<Vector3<T>>,
+    // only needed for contact between separate velocity fields
+    pub mass_gradients: Vec<Vector3<A>>,
 
-    pub reference_velocities: Vec<Vector3<T>>,
-    pub newton_direction: Vec<Vector3<T>>,
+    pub reference_velocities: Vec<Vector3<A>>,
+    pub newton_direction: Vec<Vector3<A>>,
 
     pub boundaries: Vec<Option<Boundary>>,
 
-    pub residual: Vec<Vector3<T>>,
+    pub residual: Vec<Vector3<A>>,
 
-    pub cg_direction: Vec<Vector3<T>>,
-    pub cg_conjugated: Vec<Vector3<T>>,
-}
-
-impl GridMomentum {
-    pub fn prepare_contributors(&mut self, initial_capacity: usize) {
-        self.contributors
-            .par_iter_mut()
-            .for_each(|v| v.try_lock().unwrap().clear());
-        self.contributors.resize_with(self.map.len(), || {
-            Vec::with_capacity(initial_capacity).into()
-        });
-    }
+    pub cg_direction: Vec<Vector3<A>>,
+    pub cg_conjugated: Vec<Vector3<A>>,
 }
@@ -39,6 +39,10 @@ impl<T> Mutex<T> {
     pub fn lock(&self) -> MutexGuard<'_, T> {
         self.0.lock().unwrap()
     }
+
+    pub fn into_inner(self) -> T {
+        self.0.into_inner().unwrap()
+    }
 }
 
 impl<T> From<T> for Mutex<T> {
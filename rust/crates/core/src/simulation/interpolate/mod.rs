@@ -6,6 +6,7 @@
 // license that can be found in the LICENSE_MIT file or at
 // https://opensource.org/licenses/MIT.
 
+pub mod stencil_weights;
 pub mod weights;
 
 #[cfg(test)]
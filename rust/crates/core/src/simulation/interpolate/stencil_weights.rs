@@ -0,0 +1,131 @@
+// SPDX-License-Identifier: MIT
+//
+// Copyright 2025  Algebraic UG (haftungsbeschränkt)
+//
+// Use of this source code is governed by an MIT-style
+// license that can be found in the LICENSE_MIT file or at
+// https://opensource.org/licenses/MIT.
+
+use std::array::from_fn;
+
+use blended_mpm_api::T;
+use nalgebra::Vector3;
+use rayon::{
+    iter::{IndexedParallelIterator, IntoParallelRefMutIterator, ParallelIterator},
+    slice::{ParallelSlice, ParallelSliceMut},
+};
+
+use super::weights::{
+    KERNEL_QUADRATIC_LENGTH, kernel_quadratic, kernel_quadratic_derivative,
+    position_to_shift_quadratic,
+};
+
+#[cfg(not(feature = "f64"))]
+type Lanes = wide::f32x8;
+#[cfg(feature = "f64")]
+type Lanes = wide::f64x4;
+const LANES: usize = size_of::<Lanes>() / size_of::<T>();
+
+// The quadratic stencil of a particle, valid until the particle moves.
+// The weights are separable, a node's weight is the product over the axes.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct StencilWeights {
+    pub shift: Vector3<i32>,
+    // particle position relative to the first node, in grid nodes
+    pub shifted: Vector3<T>,
+    // indexed by axis, then node
+    pub weights: [[T; KERNEL_QUADRATIC_LENGTH]; 3],
+    // derivatives by the normalized node position
+    pub derivatives: [[T; KERNEL_QUADRATIC_LENGTH]; 3],
+}
+
+impl StencilWeights {
+    pub fn new(position: &Vector3<T>, grid_node_size: T) -> Self {
+        let shift = position_to_shift_quadratic(position, grid_node_size);
+        let shifted = position / grid_node_size - shift.map(|x| x as T);
+        Self {
+            shift,
+            shifted,
+            weights: from_fn(|axis| from_fn(|i| kernel_quadratic(i as T - shifted[axis]))),
+            derivatives: from_fn(|axis| {
+                from_fn(|i| kernel_quadratic_derivative(i as T - shifted[axis]))
+            }),
+        }
+    }
+
+    // `offset` is relative to the first node, use `shift` for the actual grid index
+    pub fn weight(&self, offset: Vector3<i32>) -> T {
+        self.weights[0][offset.x as usize]
+            * self.weights[1][offset.y as usize]
+            * self.weights[2][offset.z as usize]
+    }
+
+    // in normalized units, divide by the grid node size
+    pub fn weight_gradient(&self, offset: Vector3<i32>) -> Vector3<T> {
+        let [wx, wy, wz] = from_fn(|axis| self.weights[axis][offset[axis] as usize]);
+        let [dx, dy, dz] = from_fn(|axis| self.derivatives[axis][offset[axis] as usize]);
+        Vector3::new(dx * wy * wz, wx * dy * wz, wx * wy * dz)
+    }
+
+    // Same as `new`, but the polynomials are evaluated for a batch of particles in SIMD lanes.
+    // With the particle between the first and last node, the kernel's branches are known.
+    fn new_batch(positions: &[Vector3<T>], grid_node_size: T, stencils: &mut [Self]) {
+        debug_assert!(positions.len() <= LANES && positions.len() == stencils.len());
+
+        let mut shifted: [[T; LANES]; 3] = Default::default();
+        for (lane, (position, stencil)) in positions.iter().zip(stencils.iter_mut()).enumerate() {
+            stencil.shift = position_to_shift_quadratic(position, grid_node_size);
+            stencil.shifted = position / grid_node_size - stencil.shift.map(|x| x as T);
+            for (axis, shifted) in shifted.iter_mut().enumerate() {
+                shifted[lane] = stencil.shifted[axis];
+            }
+        }
+
+        let half = Lanes::splat(0.5);
+        for (axis, shifted) in shifted.into_iter().enumerate() {
+            // offset from the center node, within [-0.5, 0.5]
+            let f = Lanes::from(shifted) - Lanes::splat(1.);
+            let weights = [
+                half * (half - f) * (half - f),
+                Lanes::splat(0.75) - f * f,
+                half * (half + f) * (half + f),
+            ]
+            .map(Lanes::to_array);
+            let derivatives = [half - f, f + f, -(half + f)].map(Lanes::to_array);
+
+            for (lane, stencil) in stencils.iter_mut().enumerate() {
+                stencil.weights[axis] = from_fn(|i| weights[i][lane]);
+                stencil.derivatives[axis] = from_fn(|i| derivatives[i][lane]);
+            }
+        }
+    }
+}
+
+pub fn stencil_weights_scalar(positions: &[Vector3<T>], grid_node_size: T) -> Vec<StencilWeights> {
+    let mut stencils = vec![StencilWeights::default(); positions.len()];
+    stencils
+        .par_iter_mut()
+        .zip(positions)
+        .for_each(|(stencil, position)| *stencil = StencilWeights::new(position, grid_node_size));
+    stencils
+}
+
+pub fn stencil_weights_simd(positions: &[Vector3<T>], grid_node_size: T) -> Vec<StencilWeights> {
+    let mut stencils = vec![StencilWeights::default(); positions.len()];
+    stencils
+        .par_chunks_mut(LANES)
+        .zip(positions.par_chunks(LANES))
+        .for_each(|(stencils, positions)| {
+            StencilWeights::new_batch(positions, grid_node_size, stencils)
+        });
+    stencils
+}
+
+// The scalar version is the reference, it can be forced for debugging.
+pub fn stencil_weights(positions: &[Vector3<T>], grid_node_size: T) -> Vec<StencilWeights> {
+    if cfg!(feature = "scalar_weights") {
+        stencil_weights_scalar(positions, grid_node_size)
+    } else {
+        stencil_weights_simd(positions, grid_node_size)
+    }
+}
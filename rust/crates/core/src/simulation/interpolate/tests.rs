@@ -6,4 +6,36 @@
 // license that can be found in the LICENSE_MIT file or at
 // https://opensource.org/licenses/MIT.
 
+use blended_mpm_api::T;
+use nalgebra::Vector3;
 
+use super::stencil_weights::{stencil_weights_scalar, stencil_weights_simd};
+
+#[test]
+fn simd_stencil_weights_match_scalar() {
+    let grid_node_size = 0.1;
+    // not a multiple of the lanes, s.t. the remainder is covered as well
+    let positions = (0..1001)
+        .map(|i| {
+            let i = i as T;
+            Vector3::new((i * 0.37).sin(), (i * 0.11).cos() * 3., i * 0.05 - 25.)
+        })
+        .collect::<Vec<_>>();
+
+    let scalar = stencil_weights_scalar(&positions, grid_node_size);
+    let simd = stencil_weights_simd(&positions, grid_node_size);
+
+    for ((scalar, simd), position) in scalar.iter().zip(&simd).zip(&positions) {
+        // `shifted` is only as exact as the position in grid nodes, this close to the kernel's
+        // branches the polynomials differ by about as much
+        let eps = 8. * T::EPSILON * (position / grid_node_size).amax().max(1.);
+        assert_eq!(scalar.shift, simd.shift);
+        assert_eq!(scalar.shifted, simd.shifted);
+        for axis in 0..3 {
+            for i in 0..3 {
+                assert!((scalar.weights[axis][i] - simd.weights[axis][i]).abs() <= eps);
+                assert!((scalar.derivatives[axis][i] - simd.derivatives[axis][i]).abs() <= eps);
+            }
+        }
+    }
+}
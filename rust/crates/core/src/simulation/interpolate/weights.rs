@@ -25,6 +25,17 @@ pub fn kernel_quadratic(x: T) -> T {
     }
 }
 
+pub fn kernel_quadratic_derivative(x: T) -> T {
+    let a = x.abs();
+    if a < 1. / 2. {
+        -2. * x
+    } else if a < 3. / 2. {
+        -(3. / 2. - a) * x.signum()
+    } else {
+        0.
+    }
+}
+
 pub fn kernel_cubic(x: T) -> T {
     let x = x.abs();
     if x < 1. {
@@ -0,0 +1,28 @@
+// SPDX-License-Identifier: MIT
+//
+// Copyright 2025  Algebraic UG (haftungsbeschränkt)
+//
+// Use of this source code is governed by an MIT-style
+// license that can be found in the LICENSE_MIT file or at
+// https://opensource.org/licenses/MIT.
+
+use std::collections::HashMap;
+
+// Estimate of the allocated heap memory, this is what grows with the scene.
+// Only the containers themselves, nested allocations have to be added separately.
+pub trait HeapBytes {
+    fn heap_bytes(&self) -> u64;
+}
+
+impl<Item> HeapBytes for Vec<Item> {
+    fn heap_bytes(&self) -> u64 {
+        (self.capacity() * size_of::<Item>()) as u64
+    }
+}
+
+impl<Key, Value, S> HeapBytes for HashMap<Key, Value, S> {
+    fn heap_bytes(&self) -> u64 {
+        // one control byte per bucket
+        (self.capacity() * (size_of::<(Key, Value)>() + 1)) as u64
+    }
+}
@@ -15,6 +15,7 @@ mod fluid;
 mod grids;
 mod interpolate;
 mod kinematic;
+mod memory;
 mod particles;
 mod simulation_local;
 mod solid;
@@ -22,4 +23,4 @@ mod state;
 
 pub use interpolate::weights;
 pub use simulation_local::SimulationLocal;
-pub use state::{Phase, PhaseInput, State};
+pub use state::{Phase, PhaseInput, State, lean::FrameContents};
@@ -11,24 +11,44 @@ use fxhash::FxHashMap;
 use nalgebra::{Matrix3, Vector3};
 use serde::{Deserialize, Serialize};
 
+use super::interpolate::stencil_weights::StencilWeights;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum ParticleParameters {
     Solid {
         mu: T,
         lambda: T,
         viscosity: T,
+        drag: T,
+        stiffness_damping: T,
     },
     Fluid {
         exponent: i32,
         bulk_modulus: T,
         viscosity: T,
+        drag: T,
+        stiffness_damping: T,
     },
 }
 
+impl ParticleParameters {
+    pub fn drag(&self) -> T {
+        match self {
+            Self::Solid { drag, .. } | Self::Fluid { drag, .. } => *drag,
+        }
+    }
+}
+
 #[derive(Default, Debug, Clone, Serialize, Deserialize)]
 pub struct Particles {
     pub sort_map: Vec<usize>,
     pub reverse_sort_map: Vec<usize>,
+    // cell keys of the last sort, not worth storing
+    #[serde(skip)]
+    pub sort_keys: Vec<u64>,
+    // stencils of the current positions, computed when first needed after the particles moved
+    #[serde(skip)]
+    pub stencil_weights: Vec<StencilWeights>,
 
     pub parameters: Vec<ParticleParameters>,
 
@@ -43,7 +63,49 @@ pub struct Particles {
 
     pub elastic_energies: Vec<T>,
     pub collider_insides: Vec<FxHashMap<usize, bool>>,
+    pub velocity_fields: Vec<Option<usize>>,
 
     pub trial_position_gradients: Vec<Matrix3<T>>,
     pub action_matrices: Vec<Matrix3<T>>,
 }
+
+impl Particles {
+    // Appends the particles of a separately constructed object, returns the index offset.
+    pub fn append(&mut self, other: Self) -> usize {
+        let offset = self.sort_map.len();
+        let Self {
+            sort_map,
+            reverse_sort_map,
+            sort_keys: _,
+            stencil_weights: _,
+            parameters,
+            masses,
+            initial_volumes,
+            positions,
+            position_gradients,
+            velocities,
+            velocity_gradients,
+            elastic_energies,
+            collider_insides,
+            velocity_fields,
+            trial_position_gradients: _,
+            action_matrices: _,
+        } = other;
+
+        self.sort_map
+            .extend(sort_map.into_iter().map(|idx| idx + offset));
+        self.reverse_sort_map
+            .extend(reverse_sort_map.into_iter().map(|idx| idx + offset));
+        self.parameters.extend(parameters);
+        self.masses.extend(masses);
+        self.initial_volumes.extend(initial_volumes);
+        self.positions.extend(positions);
+        self.position_gradients.extend(position_gradients);
+        self.velocities.extend(velocities);
+        self.velocity_gradients.extend(velocity_gradients);
+        self.elastic_energies.extend(elastic_energies);
+        self.collider_insides.extend(collider_insides);
+        self.velocity_fields.extend(velocity_fields);
+        offset
+    }
+}
@@ -9,7 +9,7 @@
 use std::{num::NonZero, sync::Arc};
 
 use anyhow::{Context, Result, ensure};
-use blended_mpm_api::{T, Task};
+use blended_mpm_api::{FrameMetadata, T, Task};
 use serde_json::{Value, from_value, to_value};
 use tracing::warn;
 
@@ -44,10 +44,16 @@ impl Simulation for SimulationLocal {
 
     fn poll(&mut self) -> Result<Option<Task>> {
         self.cache.check()?;
-        self.compute_thread
+        let mut task = self
+            .compute_thread
             .as_mut()
             .map(ComputeThread::poll)
-            .unwrap_or(Ok(Default::default()))
+            .unwrap_or(Ok(Default::default()))?;
+        if let Some(task) = &mut task {
+            task.sub_tasks.push(self.cache.store_task());
+            task.sub_tasks.extend(self.cache.eviction_task());
+        }
+        Ok(task)
     }
 
     fn start_compute(
@@ -58,8 +64,23 @@ impl Simulation for SimulationLocal {
         next_frame: usize,
         number_of_frames: usize,
         max_bytes_on_disk: u64,
+        max_bytes_in_memory: u64,
+        number_of_threads: usize,
+        low_priority: bool,
+        store_queue_depth: usize,
+        frame_codec: Value,
+        frame_policy: Value,
+        eviction_policy: Value,
     ) -> Result<()> {
         self.cache.set_max_bytes_on_disk(max_bytes_on_disk);
+        self.cache.set_max_bytes_in_memory(max_bytes_in_memory);
+        self.cache.set_store_queue_depth(store_queue_depth);
+        self.cache
+            .set_frame_codec(from_value(frame_codec).context("Unknown frame codec")?);
+        self.cache
+            .set_frame_policy(from_value(frame_policy).context("Invalid frame policy")?);
+        self.cache
+            .set_eviction_policy(from_value(eviction_policy).context("Unknown eviction policy")?);
 
         let Some(number_of_frames) = NonZero::new(number_of_frames) else {
             warn!("asked to compute 0 frames");
@@ -74,6 +95,8 @@ impl Simulation for SimulationLocal {
 
         self.pause_compute();
         self.cache.check()?;
+        // frames after the last checkpoint are recomputed
+        let next_frame = self.cache.resume_frame(next_frame)?;
         self.cache.drop_frames(next_frame)?;
         self.compute_thread = Some(ComputeThread::new(
             self.cache.clone(),
@@ -87,6 +110,8 @@ impl Simulation for SimulationLocal {
             },
             number_of_frames,
             next_frame,
+            number_of_threads,
+            low_priority,
         )?);
         Ok(())
     }
@@ -99,7 +124,15 @@ impl Simulation for SimulationLocal {
         self.cache.available_frames()
     }
 
+    fn manifest(&self) -> Vec<FrameMetadata> {
+        self.cache.manifest()
+    }
+
     fn available_attributes(&self, frame: usize) -> Result<Vec<Value>> {
+        // scrubbing over evicted frames shows nothing instead of failing
+        if self.cache.is_evicted(frame) {
+            return Ok(Vec::new());
+        }
         self.cache
             .available_attributes(frame)?
             .into_iter()
@@ -147,6 +180,8 @@ impl Simulation for SimulationLocal {
                     }
                 })
             }
+            Attribute::Diagnostics(attribute) => Ok(self.cache.fetch_flat_diagnostics(attribute)),
+            _ if self.cache.is_evicted(frame) => Ok(Vec::new()),
             attribute => self.cache.fetch_flat_attribute(frame, attribute),
         }
     }
@@ -13,6 +13,7 @@ use std::{
 
 use crate::{
     api::{GlobalSettings, Mesh, ObjectSettingsSolid},
+    math::random::random_stream,
     report::Report,
     simulation::{
         elastic::{
@@ -33,6 +34,7 @@ use super::{kinematic::Kinematic, particles::Particles};
 #[derive(Clone, Serialize, Deserialize)]
 pub struct Solid {
     pub particles: Vec<usize>,
+    pub velocity_field: Option<usize>,
 }
 
 pub struct SolidConstruction<'a> {
@@ -44,6 +46,7 @@ pub struct SolidConstruction<'a> {
     pub object_settings: ObjectSettingsSolid,
     pub mesh: &'a Mesh,
     pub particles: &'a mut Particles,
+    pub velocity_field: Option<usize>,
 }
 
 impl Solid {
@@ -52,7 +55,12 @@ impl Solid {
             name,
             run,
             report,
-            settings: GlobalSettings { particle_size, .. },
+            settings:
+                GlobalSettings {
+                    particle_size,
+                    seed,
+                    ..
+                },
             kinematic:
                 Kinematic {
                     position,
@@ -68,9 +76,13 @@ impl Solid {
                     viscosity,
                     dilation,
                     randomness,
+                    drag,
+                    stiffness_damping,
+                    separate_velocity_field: _,
                 },
             mesh,
             particles,
+            velocity_field,
         }: SolidConstruction,
     ) -> Result<Self> {
         info!("solid object");
@@ -102,6 +114,7 @@ impl Solid {
             report.clone(),
             *particle_size * dilation,
             randomness,
+            random_stream(*seed, name),
         )?;
         let first_idx = particles.sort_map.len();
         report.step();
@@ -110,6 +123,8 @@ impl Solid {
             let Particles {
                 sort_map,
                 reverse_sort_map,
+                sort_keys: _,
+                stencil_weights: _,
                 parameters,
                 masses,
                 initial_volumes,
@@ -119,6 +134,7 @@ impl Solid {
                 velocity_gradients,
                 elastic_energies,
                 collider_insides,
+                velocity_fields,
                 trial_position_gradients: _,
                 action_matrices: _,
             } = particles;
@@ -137,6 +153,8 @@ impl Solid {
                     mu,
                     lambda,
                     viscosity,
+                    drag,
+                    stiffness_damping,
                 },
             );
             masses.resize(n, mass);
@@ -145,6 +163,7 @@ impl Solid {
             velocity_gradients.resize(n, velocity_gradient);
             elastic_energies.resize(n, elastic_energy);
             collider_insides.resize(n, Default::default());
+            velocity_fields.resize(n, velocity_field);
 
             positions.extend(
                 samples
@@ -160,6 +179,7 @@ impl Solid {
 
         Ok(Self {
             particles: (first_idx..first_idx + samples.len()).collect(),
+            velocity_field,
         })
     }
 }
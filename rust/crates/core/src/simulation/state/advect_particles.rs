@@ -57,6 +57,7 @@ impl State {
                     Ok(())
                 },
             )?;
+        self.particles.stencil_weights.clear();
 
         Ok(self)
     }
@@ -35,6 +35,8 @@ pub enum Attribute {
     },
     GridMomentums(AttributeGridMomentums),
     GridColliderDistance(AttributeGridColliderDistance),
+    // These are time series over all stored frames.
+    Diagnostics(AttributeDiagnostics),
 }
 
 #[derive(EnumIter, Serialize, Deserialize)]
@@ -45,6 +47,21 @@ pub enum AttributeSetting {
     Gravity,
 }
 
+#[derive(EnumIter, Serialize, Deserialize)]
+pub enum AttributeDiagnostics {
+    Times,
+    KineticEnergies,
+    ElasticEnergies,
+    LinearMomenta,
+    AngularMomenta,
+    Masses,
+    FreeGridMasses,
+    MemoryUsages,
+    // Every collider and object, those on the shared free grid report the mass of their
+    // particles, which is their part of `FreeGridMasses`.
+    GridMasses(String),
+}
+
 #[derive(EnumIter, Serialize, Deserialize)]
 pub enum AttributeGridColliderDistance {
     Positions,
@@ -59,6 +76,10 @@ pub enum AttributeGridMomentums {
         name: String,
         attribute: AttributeGridMomentum,
     },
+    Separated {
+        name: String,
+        attribute: AttributeGridMomentum,
+    },
 }
 
 #[derive(EnumIter, Serialize, Deserialize)]
@@ -117,6 +138,16 @@ impl State {
     pub fn available_attributes(&self) -> impl Iterator<Item = Attribute> + '_ {
         empty()
             .chain(AttributeSetting::iter().map(Attribute::Setting))
+            .chain(
+                AttributeDiagnostics::iter()
+                    .filter(|attribute| !matches!(attribute, AttributeDiagnostics::GridMasses(_)))
+                    .chain(
+                        self.name_map
+                            .keys()
+                            .map(|name| AttributeDiagnostics::GridMasses(name.clone())),
+                    )
+                    .map(Attribute::Diagnostics),
+            )
             .chain(AttributeGridColliderDistance::iter().map(Attribute::GridColliderDistance))
             .chain(
                 AttributeGridMomentum::iter()
@@ -139,6 +170,22 @@ impl State {
                     })
                     .map(Attribute::GridMomentums),
             )
+            .chain(
+                self.name_map
+                    .iter()
+                    .filter_map(move |(name, object_idx)| {
+                        self.velocity_field(object_idx).map(|_| name.clone())
+                    })
+                    .flat_map(|name| {
+                        AttributeGridMomentum::iter().map(move |attribute| {
+                            AttributeGridMomentums::Separated {
+                                name: name.clone(),
+                                attribute,
+                            }
+                        })
+                    })
+                    .map(Attribute::GridMomentums),
+            )
             .chain(self.name_map.iter().flat_map(|(name, object_idx)| {
                 match object_idx {
                     ObjectIndex::Solid(_) => AttributeSolid::iter()
@@ -168,6 +215,7 @@ impl State {
         let flat_attribute = match attribute {
             Attribute::Setting(_) => bail!("attribute should have been handled before"),
             Attribute::Mesh { .. } => bail!("attribute should have been handled before"),
+            Attribute::Diagnostics(_) => bail!("attribute should have been handled before"),
             Attribute::Object { name, attribute } => {
                 let object_idx = self.name_map.get(&name).context("Missing object")?;
                 match (attribute, object_idx) {
@@ -292,17 +340,15 @@ impl State {
             Attribute::GridColliderDistance(attribute) => match attribute {
                 AttributeGridColliderDistance::Positions => self
                     .grid_collider_distances
-                    .keys()
-                    .map(|grid_node_idx| grid_node_idx.map(|i| i as T) * grid_node_size)
+                    .iter()
+                    .map(|(grid_node_idx, _)| grid_node_idx.map(|i| i as T) * grid_node_size)
                     .flat_map(|position| position.flat())
                     .collect(),
                 AttributeGridColliderDistance::ColliderDistances(collider_idx) => self
                     .grid_collider_distances
-                    .values()
-                    .map(|grid_node| {
+                    .iter()
+                    .map(|(_, grid_node)| {
                         grid_node
-                            .try_lock()
-                            .unwrap()
                             .weighted_distances
                             .get(&collider_idx)
                             .map(|weighted_distance| weighted_distance.distance)
@@ -311,11 +357,9 @@ impl State {
                     .collect(),
                 AttributeGridColliderDistance::ColliderDistanceNormals(collider_idx) => self
                     .grid_collider_distances
-                    .values()
-                    .flat_map(|grid_node| {
+                    .iter()
+                    .flat_map(|(_, grid_node)| {
                         grid_node
-                            .try_lock()
-                            .unwrap()
                             .weighted_distances
                             .get(&collider_idx)
                             .map(|weighted_distance| weighted_distance.normal)
@@ -326,22 +370,25 @@ impl State {
             },
             Attribute::GridMomentums(attribute) => {
                 let fetch_flat_attribute_grid_momentum =
-                    |grid: &GridMomentum, attribute: AttributeGridMomentum| match attribute {
-                        AttributeGridMomentum::Masses => grid.masses.clone(),
-                        AttributeGridMomentum::Positions => {
-                            // after deserializing the hashmap has a different iteration order
-                            let mut messed_up = grid.map.iter().collect::<Vec<_>>();
-                            messed_up.sort_unstable_by_key(|(_, i)| **i);
-                            messed_up
-                                .into_iter()
-                                .map(|(grid_node_idx, _)| {
-                                    grid_node_idx.map(|i| i as T) * grid_node_size
-                                })
+                    |grid: &GridMomentum, attribute: AttributeGridMomentum| {
+                        // skip the unused nodes of a block
+                        let used = grid
+                            .masses
+                            .iter()
+                            .enumerate()
+                            .filter(|(_, mass)| **mass > 0.)
+                            .map(|(idx, _)| idx);
+                        match attribute {
+                            AttributeGridMomentum::Masses => {
+                                used.map(|idx| grid.masses[idx] as T).collect()
+                            }
+                            AttributeGridMomentum::Positions => used
+                                .map(|idx| grid.map.key(idx).map(|i| i as T) * grid_node_size)
                                 .flat_map(|position| position.flat())
-                                .collect()
-                        }
-                        AttributeGridMomentum::Velocities => {
-                            grid.velocities.iter().flat_map(Flat3::flat).collect()
+                                .collect(),
+                            AttributeGridMomentum::Velocities => used
+                                .flat_map(|idx| grid.velocities[idx].cast::<T>().flat())
+                                .collect(),
                         }
                     };
                 match attribute {
@@ -358,6 +405,16 @@ impl State {
                             attribute,
                         )
                     }
+                    AttributeGridMomentums::Separated { name, attribute } => {
+                        let object_idx = self.name_map.get(&name).context("Missing object")?;
+                        let field_idx = self
+                            .velocity_field(object_idx)
+                            .context("Object has no separate velocity field")?;
+                        fetch_flat_attribute_grid_momentum(
+                            &self.grid_object_momentums[field_idx],
+                            attribute,
+                        )
+                    }
                 }
             }
         };
@@ -0,0 +1,216 @@
+// SPDX-License-Identifier: MIT
+//
+// Copyright 2025  Algebraic UG (haftungsbeschränkt)
+//
+// Use of this source code is governed by an MIT-style
+// license that can be found in the LICENSE_MIT file or at
+// https://opensource.org/licenses/MIT.
+
+// Before frames had a header, they were the bare state of the first version of the addon.
+// Those are migrated as checkpoints, only with what's needed to continue computing.
+
+use anyhow::{Context, Result, ensure};
+use bincode::deserialize;
+use blended_mpm_api::T;
+use fxhash::FxHashMap;
+use nalgebra::{Matrix3, Vector3};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+
+use crate::{
+    api::SurfaceSample,
+    simulation::{
+        collider::{Collider, bounding_sphere},
+        fluid::Fluid,
+        kinematic::{Kinematic, ScriptedMovement},
+        particles::{ParticleParameters, Particles},
+        solid::Solid,
+    },
+};
+
+use super::{ObjectIndex, Phase, State};
+
+#[derive(Deserialize)]
+enum BaselineParticleParameters {
+    Solid {
+        mu: T,
+        lambda: T,
+        viscosity: T,
+    },
+    Fluid {
+        exponent: i32,
+        bulk_modulus: T,
+        viscosity: T,
+    },
+}
+
+#[derive(Deserialize)]
+struct BaselineParticles {
+    sort_map: Vec<usize>,
+    reverse_sort_map: Vec<usize>,
+    parameters: Vec<BaselineParticleParameters>,
+    masses: Vec<T>,
+    initial_volumes: Vec<T>,
+    positions: Vec<Vector3<T>>,
+    position_gradients: Vec<Matrix3<T>>,
+    velocities: Vec<Vector3<T>>,
+    velocity_gradients: Vec<Matrix3<T>>,
+    elastic_energies: Vec<T>,
+    collider_insides: Vec<FxHashMap<usize, bool>>,
+    trial_position_gradients: Vec<Matrix3<T>>,
+    action_matrices: Vec<Matrix3<T>>,
+}
+
+// solids and fluids
+#[derive(Deserialize)]
+struct BaselineObject {
+    particles: Vec<usize>,
+}
+
+#[derive(Deserialize)]
+struct BaselineCollider {
+    sticky_factor: T,
+    friction_factor: T,
+    surface_samples: Vec<SurfaceSample>,
+    kinematic: Kinematic,
+    has_moved: bool,
+    scripted_movements: Vec<ScriptedMovement>,
+}
+
+// The grids that followed aren't read, the next substep scatters them again.
+#[derive(Deserialize)]
+struct BaselineState {
+    time: f64,
+    phase: Phase,
+    name_map: BTreeMap<String, ObjectIndex>,
+    particles: BaselineParticles,
+    solid_objects: Vec<BaselineObject>,
+    fluid_objects: Vec<BaselineObject>,
+    collider_objects: Vec<BaselineCollider>,
+}
+
+impl State {
+    // Everything added since, e.g. drag or restitution, is off as it was back then.
+    pub fn from_baseline_frame(bytes: &[u8]) -> Result<Self> {
+        let BaselineState {
+            time,
+            phase,
+            name_map,
+            particles,
+            solid_objects,
+            fluid_objects,
+            collider_objects,
+        } = deserialize(bytes).context("baseline state deserialization")?;
+        ensure!(
+            phase == Phase::default(),
+            "Baseline frame wasn't stored between substeps"
+        );
+
+        let BaselineParticles {
+            sort_map,
+            reverse_sort_map,
+            parameters,
+            masses,
+            initial_volumes,
+            positions,
+            position_gradients,
+            velocities,
+            velocity_gradients,
+            elastic_energies,
+            collider_insides,
+            trial_position_gradients,
+            action_matrices,
+        } = particles;
+        let particles = Particles {
+            velocity_fields: vec![None; sort_map.len()],
+            sort_map,
+            reverse_sort_map,
+            sort_keys: Vec::new(),
+            stencil_weights: Vec::new(),
+            parameters: parameters
+                .into_iter()
+                .map(|parameters| match parameters {
+                    BaselineParticleParameters::Solid {
+                        mu,
+                        lambda,
+                        viscosity,
+                    } => ParticleParameters::Solid {
+                        mu,
+                        lambda,
+                        viscosity,
+                        drag: 0.,
+                        stiffness_damping: 0.,
+                    },
+                    BaselineParticleParameters::Fluid {
+                        exponent,
+                        bulk_modulus,
+                        viscosity,
+                    } => ParticleParameters::Fluid {
+                        exponent,
+                        bulk_modulus,
+                        viscosity,
+                        drag: 0.,
+                        stiffness_damping: 0.,
+                    },
+                })
+                .collect(),
+            masses,
+            initial_volumes,
+            positions,
+            position_gradients,
+            velocities,
+            velocity_gradients,
+            elastic_energies,
+            collider_insides,
+            trial_position_gradients,
+            action_matrices,
+        };
+
+        let collider_objects: Vec<Collider> = collider_objects
+            .into_iter()
+            .map(|collider| {
+                let (bounding_center, bounding_radius) = bounding_sphere(&collider.surface_samples);
+                Collider {
+                    sticky_factor: collider.sticky_factor,
+                    friction_factor: collider.friction_factor,
+                    static_friction_factor: 0.,
+                    restitution: 0.,
+                    adhesion_velocity: 0.,
+                    surface_samples: collider.surface_samples,
+                    bounding_center,
+                    bounding_radius,
+                    kinematic: collider.kinematic,
+                    has_moved: collider.has_moved,
+                    culled: false,
+                    scripted_movements: collider.scripted_movements,
+                }
+            })
+            .collect();
+
+        Ok(Self {
+            time,
+            phase,
+            name_map,
+            particles,
+            solid_objects: solid_objects
+                .into_iter()
+                .map(|solid| Solid {
+                    particles: solid.particles,
+                    velocity_field: None,
+                })
+                .collect(),
+            fluid_objects: fluid_objects
+                .into_iter()
+                .map(|fluid| Fluid {
+                    particles: fluid.particles,
+                    velocity_field: None,
+                })
+                .collect(),
+            grid_collider_distances: Default::default(),
+            grid_momentum: Default::default(),
+            grid_collider_momentums: vec![Default::default(); collider_objects.len()],
+            grid_object_momentums: Vec::new(),
+            collider_objects,
+        })
+    }
+}
@@ -11,13 +11,9 @@ use blended_mpm_api::T;
 use fxhash::FxHashMap;
 use nalgebra::{Matrix4, Vector3, Vector4};
 use rayon::iter::{IndexedParallelIterator, IntoParallelRefIterator, ParallelIterator};
-use std::{array::from_fn, collections::hash_map::Entry};
+use std::collections::hash_map::Entry;
 
-use crate::{
-    math::{NORMALIZATION_EPS, safe_inverse::SafeInverse},
-    simulation::weights::kernel_quadratic,
-    weights::KERNEL_QUADRATIC_LENGTH,
-};
+use crate::math::{NORMALIZATION_EPS, safe_inverse::SafeInverse};
 
 use super::{PhaseInput, State, check_shifted_quadratic, profile};
 
@@ -47,37 +43,30 @@ impl State {
             }
         }
 
+        self.prepare_stencil_weights(grid_node_size);
         self.particles
             .positions
             .par_iter()
+            .zip(&self.particles.stencil_weights)
             .zip(&mut self.particles.velocities)
             .zip(&mut self.particles.collider_insides)
-            .for_each(|((position, velocity), collider_inside)| {
+            .for_each(|(((position, stencil), velocity), collider_inside)| {
                 let mut distance_helpers: FxHashMap<usize, DistanceHelper> = Default::default();
 
-                let normalized = position / grid_node_size;
-                let shift = (normalized - Vector3::repeat(0.5)).map(T::floor);
-                let shifted = normalized - shift;
-
+                let shifted = stencil.shifted;
                 debug_assert!(check_shifted_quadratic(shifted));
 
-                let [x_weights, y_weights, z_weights]: [[T; KERNEL_QUADRATIC_LENGTH]; 3] = {
-                    [
-                        from_fn(|i| kernel_quadratic(shifted.x - i as T)),
-                        from_fn(|i| kernel_quadratic(shifted.y - i as T)),
-                        from_fn(|i| kernel_quadratic(shifted.z - i as T)),
-                    ]
-                };
+                let [x_weights, y_weights, z_weights] = &stencil.weights;
 
+                let distances = self.grid_collider_distances.stencil(stencil.shift);
                 for (i, x_weight) in x_weights.iter().enumerate() {
                     for (j, y_weight) in y_weights.iter().enumerate() {
                         for (k, z_weight) in z_weights.iter().enumerate() {
                             let weight = x_weight * y_weight * z_weight;
-                            let grid_idx = shift.map(|x| x as i32)
-                                + Vector3::new(i as i32, j as i32, k as i32);
+                            let grid_idx =
+                                stencil.shift + Vector3::new(i as i32, j as i32, k as i32);
 
-                            let Some(grid_node) = self.grid_collider_distances.get(&grid_idx)
-                            else {
+                            let Some(grid_node) = distances.get(&grid_idx) else {
                                 continue;
                             };
 
@@ -88,7 +77,7 @@ impl State {
                                 k as T - shifted.z,
                             );
                             for (collider_idx, weighted_distance) in
-                                grid_node.lock().weighted_distances.iter()
+                                grid_node.weighted_distances.iter()
                             {
                                 let distance_helper =
                                     distance_helpers.entry(*collider_idx).or_default();
@@ -146,13 +135,13 @@ impl State {
                         // Stick with the side and receive penetration penalty.
                         Entry::Occupied(occupied_entry) => {
                             if occupied_entry.get() ^ (distance < 0.) {
-                                *velocity -= normal * normal.dot(velocity);
-                                *velocity += normal
-                                    * (self.collider_objects[collider_idx]
-                                        .kinematic
-                                        .point_velocity_from_world(*position)
-                                        .dot(&normal)
-                                        - distance / time_step);
+                                *velocity = self.collider_objects[collider_idx].penalty_velocity(
+                                    *position,
+                                    *velocity,
+                                    normal * -distance.signum(),
+                                    distance.abs(),
+                                    time_step,
+                                );
                             }
                         }
                         // Collider is new, accept the side
@@ -10,9 +10,6 @@ use anyhow::Result;
 use blended_mpm_api::T;
 use nalgebra::{Matrix3, Vector3};
 use rayon::iter::{IndexedParallelIterator, IntoParallelRefIterator, ParallelIterator};
-use std::array::from_fn;
-
-use crate::{simulation::weights::kernel_quadratic, weights::KERNEL_QUADRATIC_LENGTH};
 
 use super::{PhaseInput, State, check_shifted_quadratic, find_worst_incompatibility, profile};
 
@@ -21,58 +18,62 @@ impl State {
     pub(super) fn collect_velocity(mut self, phase_input: PhaseInput) -> Result<Self> {
         profile!("collect_velocity");
         let grid_node_size = phase_input.setup.settings.grid_node_size;
+        let time_step = phase_input.time_step;
+        self.prepare_stencil_weights(grid_node_size);
         self.particles
             .positions
             .par_iter()
+            .zip(&self.particles.stencil_weights)
             .zip(&self.particles.collider_insides)
+            .zip(&self.particles.velocity_fields)
+            .zip(&self.particles.parameters)
             .zip(&mut self.particles.velocities)
             .zip(&mut self.particles.velocity_gradients)
             .for_each(
-                |(((position, collider_inside), velocity), velocity_gradient)| {
+                |(
+                    (
+                        ((((position, stencil), collider_inside), velocity_field), parameters),
+                        velocity,
+                    ),
+                    velocity_gradient,
+                )| {
                     *velocity = Vector3::zeros();
                     *velocity_gradient = Matrix3::zeros();
 
-                    let normalized = position / grid_node_size;
-                    let shift = (normalized - Vector3::repeat(0.5)).map(T::floor);
-                    let shifted = normalized - shift;
-
-                    debug_assert!(check_shifted_quadratic(shifted));
+                    debug_assert!(check_shifted_quadratic(stencil.shifted));
 
-                    let [x_weights, y_weights, z_weights]: [[T; KERNEL_QUADRATIC_LENGTH]; 3] = {
-                        [
-                            from_fn(|i| kernel_quadratic(shifted.x - i as T)),
-                            from_fn(|i| kernel_quadratic(shifted.y - i as T)),
-                            from_fn(|i| kernel_quadratic(shifted.z - i as T)),
-                        ]
-                    };
+                    let [x_weights, y_weights, z_weights] = &stencil.weights;
 
+                    let distances = self.grid_collider_distances.stencil(stencil.shift);
+                    let free = self.grid_momentum.map.stencil(stencil.shift);
                     for (i, x_weight) in x_weights.iter().enumerate() {
                         for (j, y_weight) in y_weights.iter().enumerate() {
                             for (k, z_weight) in z_weights.iter().enumerate() {
                                 let weight = x_weight * y_weight * z_weight;
-                                let grid_idx = shift.map(|x| x as i32)
-                                    + Vector3::new(i as i32, j as i32, k as i32);
+                                let grid_idx =
+                                    stencil.shift + Vector3::new(i as i32, j as i32, k as i32);
 
-                                let incompatibility = self
-                                    .grid_collider_distances
-                                    .get(&grid_idx)
-                                    .and_then(|grid_node| {
-                                        find_worst_incompatibility(
-                                            collider_inside,
-                                            &grid_node.lock(),
-                                        )
+                                let incompatibility =
+                                    distances.get(&grid_idx).and_then(|grid_node| {
+                                        find_worst_incompatibility(collider_inside, grid_node)
                                     });
                                 let grid_node_position = grid_idx.map(|i| i as T) * grid_node_size;
                                 let to_grid_node = grid_node_position - position;
 
-                                let grid = if let Some(collider_idx) = incompatibility {
-                                    &self.grid_collider_momentums[collider_idx]
-                                } else {
-                                    &self.grid_momentum
+                                let (grid, grid_idx) = match (incompatibility, velocity_field) {
+                                    (Some(collider_idx), _) => {
+                                        let grid = &self.grid_collider_momentums[collider_idx];
+                                        (grid, grid.map.get(&grid_idx))
+                                    }
+                                    (None, Some(field_idx)) => {
+                                        let grid = &self.grid_object_momentums[*field_idx];
+                                        (grid, grid.map.get(&grid_idx))
+                                    }
+                                    (None, None) => (&self.grid_momentum, free.get(&grid_idx)),
                                 };
 
-                                let grid_idx = grid.map.get(&grid_idx).expect("missing node");
-                                let grid_velocity = grid.velocities[*grid_idx];
+                                let grid_idx = grid_idx.expect("missing node");
+                                let grid_velocity = grid.velocities[grid_idx].cast::<T>();
                                 *velocity += grid_velocity * weight;
                                 *velocity_gradient +=
                                     (grid_velocity * weight) * to_grid_node.transpose();
@@ -81,6 +82,11 @@ impl State {
                     }
 
                     *velocity_gradient *= 4. / grid_node_size / grid_node_size;
+
+                    // mass-proportional damping, implicit to stay stable for any time step
+                    let drag_factor = 1. / (1. + parameters.drag() * time_step);
+                    *velocity *= drag_factor;
+                    *velocity_gradient *= drag_factor;
                 },
             );
 
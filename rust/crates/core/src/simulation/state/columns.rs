@@ -0,0 +1,193 @@
+// SPDX-License-Identifier: MIT
+//
+// Copyright 2025  Algebraic UG (haftungsbeschränkt)
+//
+// Use of this source code is governed by an MIT-style
+// license that can be found in the LICENSE_MIT file or at
+// https://opensource.org/licenses/MIT.
+
+use anyhow::{Context, Result, bail};
+use bincode::{deserialize, serialize};
+use serde::{Deserialize, Serialize};
+use strum::EnumIter;
+
+use crate::simulation::particles::Particles;
+
+use super::{
+    State,
+    attributes::{Attribute, AttributeFluid, AttributeObject, AttributeSolid},
+    lean::{FrameContents, LeanAttribute},
+};
+
+// Frames are stored in columns s.t. an attribute is read without decoding the whole state.
+#[derive(
+    Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, EnumIter, Serialize, Deserialize,
+)]
+pub enum Column {
+    // time, phase, sort maps and the objects, needed by every attribute
+    Objects,
+    Positions,
+    Masses,
+    Velocities,
+    PositionGradients,
+    ElasticEnergies,
+    ColliderInsides,
+    Grids,
+    // the particle data only needed to continue computing
+    Rest,
+}
+
+impl Column {
+    pub fn is_stored(self, contents: &FrameContents) -> bool {
+        match self {
+            Self::Objects | Self::Positions => *contents != FrameContents::Evicted,
+            Self::Masses => contents.keeps(LeanAttribute::Masses),
+            Self::Velocities => contents.keeps(LeanAttribute::Velocities),
+            Self::PositionGradients => contents.keeps(LeanAttribute::PositionGradients),
+            Self::ElasticEnergies => contents.keeps(LeanAttribute::ElasticEnergies),
+            Self::ColliderInsides => contents.keeps(LeanAttribute::ColliderInsides),
+            Self::Grids => contents.keeps(LeanAttribute::Grids),
+            Self::Rest => *contents == FrameContents::Checkpoint,
+        }
+    }
+
+    pub fn for_attribute(attribute: &Attribute) -> Vec<Self> {
+        let column = match attribute {
+            Attribute::Setting(_) | Attribute::Mesh { .. } | Attribute::Diagnostics(_) => None,
+            Attribute::GridMomentums(_) | Attribute::GridColliderDistance(_) => Some(Self::Grids),
+            Attribute::Object { attribute, .. } => match attribute {
+                AttributeObject::Solid(attribute) => Some(match attribute {
+                    AttributeSolid::Positions => Self::Positions,
+                    AttributeSolid::Masses | AttributeSolid::InitialVolumes => Self::Masses,
+                    AttributeSolid::Velocities => Self::Velocities,
+                    AttributeSolid::PositionGradients => Self::PositionGradients,
+                    AttributeSolid::Transformations => {
+                        return vec![Self::Objects, Self::Positions, Self::PositionGradients];
+                    }
+                    AttributeSolid::ElasticEnergies => Self::ElasticEnergies,
+                    AttributeSolid::ColliderInsides(_) => Self::ColliderInsides,
+                }),
+                AttributeObject::Fluid(attribute) => Some(match attribute {
+                    AttributeFluid::Positions => Self::Positions,
+                    AttributeFluid::Velocities => Self::Velocities,
+                    AttributeFluid::Transformations => {
+                        return vec![Self::Objects, Self::Positions, Self::PositionGradients];
+                    }
+                    AttributeFluid::ColliderInsides(_) => Self::ColliderInsides,
+                    AttributeFluid::Pressures => Self::ElasticEnergies,
+                }),
+                AttributeObject::Collider(_) => None,
+            },
+        };
+        [Self::Objects].into_iter().chain(column).collect()
+    }
+}
+
+impl State {
+    pub fn column_bytes(&self, column: Column) -> Result<Vec<u8>> {
+        let ps = &self.particles;
+        let bytes = match column {
+            Column::Objects => serialize(&(
+                self.time,
+                self.phase,
+                &self.name_map,
+                &ps.sort_map,
+                &ps.reverse_sort_map,
+                &self.solid_objects,
+                &self.fluid_objects,
+                &self.collider_objects,
+            )),
+            Column::Positions => serialize(&ps.positions),
+            Column::Masses => serialize(&(&ps.masses, &ps.initial_volumes)),
+            Column::Velocities => serialize(&ps.velocities),
+            Column::PositionGradients => serialize(&ps.position_gradients),
+            Column::ElasticEnergies => serialize(&ps.elastic_energies),
+            Column::ColliderInsides => serialize(&ps.collider_insides),
+            Column::Grids => serialize(&(
+                &self.grid_collider_distances,
+                &self.grid_momentum,
+                &self.grid_collider_momentums,
+                &self.grid_object_momentums,
+            )),
+            Column::Rest => serialize(&(
+                &ps.parameters,
+                &ps.velocity_gradients,
+                &ps.velocity_fields,
+                &ps.trial_position_gradients,
+                &ps.action_matrices,
+            )),
+        };
+        bytes.with_context(|| format!("serializing column {column:?}"))
+    }
+
+    // The other columns are merged into the state of the objects column.
+    pub fn from_objects_column(bytes: &[u8]) -> Result<Self> {
+        let (
+            time,
+            phase,
+            name_map,
+            sort_map,
+            reverse_sort_map,
+            solid_objects,
+            fluid_objects,
+            collider_objects,
+        ) = deserialize(bytes).context("deserializing column Objects")?;
+        Ok(Self {
+            time,
+            phase,
+            name_map,
+            particles: Particles {
+                sort_map,
+                reverse_sort_map,
+                ..Default::default()
+            },
+            solid_objects,
+            fluid_objects,
+            collider_objects,
+            grid_collider_distances: Default::default(),
+            grid_momentum: Default::default(),
+            grid_collider_momentums: Vec::new(),
+            grid_object_momentums: Vec::new(),
+        })
+    }
+
+    pub fn merge_column(&mut self, column: Column, bytes: &[u8]) -> Result<()> {
+        let context = || format!("deserializing column {column:?}");
+        let ps = &mut self.particles;
+        match column {
+            Column::Objects => bail!("the objects column is read first"),
+            Column::Positions => ps.positions = deserialize(bytes).with_context(context)?,
+            Column::Masses => {
+                (ps.masses, ps.initial_volumes) = deserialize(bytes).with_context(context)?
+            }
+            Column::Velocities => ps.velocities = deserialize(bytes).with_context(context)?,
+            Column::PositionGradients => {
+                ps.position_gradients = deserialize(bytes).with_context(context)?
+            }
+            Column::ElasticEnergies => {
+                ps.elastic_energies = deserialize(bytes).with_context(context)?
+            }
+            Column::ColliderInsides => {
+                ps.collider_insides = deserialize(bytes).with_context(context)?
+            }
+            Column::Grids => {
+                (
+                    self.grid_collider_distances,
+                    self.grid_momentum,
+                    self.grid_collider_momentums,
+                    self.grid_object_momentums,
+                ) = deserialize(bytes).with_context(context)?
+            }
+            Column::Rest => {
+                (
+                    ps.parameters,
+                    ps.velocity_gradients,
+                    ps.velocity_fields,
+                    ps.trial_position_gradients,
+                    ps.action_matrices,
+                ) = deserialize(bytes).with_context(context)?
+            }
+        }
+        Ok(())
+    }
+}
@@ -7,8 +7,8 @@
 // https://opensource.org/licenses/MIT.
 
 use anyhow::Result;
-use blended_mpm_api::T;
-use rayon::iter::{IndexedParallelIterator, IntoParallelIterator, ParallelIterator};
+use blended_mpm_api::{A, T};
+use rayon::iter::{IndexedParallelIterator, IntoParallelRefIterator, ParallelIterator};
 
 use crate::simulation::grids::Boundary;
 
@@ -16,7 +16,7 @@ use super::{PhaseInput, State, profile};
 
 impl State {
     // Conform the collider's grids to their scripted velocity,
-    // taking stickiness and friction into account.
+    // taking stickiness, friction, restitution and adhesion into account.
     pub(super) fn conform_to_colliders(mut self, phase_input: PhaseInput) -> Result<Self> {
         profile!("conform_to_colliders");
         let grid_node_size = phase_input.setup.settings.grid_node_size;
@@ -27,52 +27,57 @@ impl State {
             .boundaries
             .resize(self.grid_momentum.map.len(), Default::default());
 
-        for (collider_idx, (collider, grid_momentum)) in self
-            .collider_objects
-            .iter()
-            .zip(self.grid_collider_momentums.iter_mut())
+        // many colliders are typically small, so they're handled in parallel too
+        self.collider_objects
+            .par_iter()
+            .zip(&mut self.grid_collider_momentums)
             .enumerate()
-        {
-            // TODO: this isn't needed for explicit integration
-            grid_momentum
-                .boundaries
-                .resize(grid_momentum.map.len(), Default::default());
-            let keys = grid_momentum.map.keys().collect::<Vec<_>>();
-            keys.into_par_iter()
-                .zip(&mut grid_momentum.velocities)
-                .zip(&mut grid_momentum.boundaries)
-                .for_each(|((grid_idx, velocity), boundary)| {
-                    let collider_distances = self
-                        .grid_collider_distances
-                        .get(grid_idx)
-                        .expect("missing distance node");
-                    let distance_node = collider_distances.try_lock().unwrap();
+            .for_each(|(collider_idx, (collider, grid_momentum))| {
+                // TODO: this isn't needed for explicit integration
+                grid_momentum
+                    .boundaries
+                    .resize(grid_momentum.map.len(), Default::default());
+                grid_momentum
+                    .map
+                    .par_keys()
+                    .zip(&mut grid_momentum.velocities)
+                    .zip(&mut grid_momentum.boundaries)
+                    .for_each(|((grid_idx, velocity), boundary)| {
+                        // unused nodes of a block don't need to be close to the collider
+                        let Some(collider_distances) = self.grid_collider_distances.get(&grid_idx)
+                        else {
+                            *boundary = None;
+                            return;
+                        };
 
-                    let Some(weighted_distance) =
-                        distance_node.weighted_distances.get(&collider_idx)
-                    else {
-                        *boundary = None;
-                        return;
-                    };
+                        let Some(weighted_distance) =
+                            collider_distances.weighted_distances.get(&collider_idx)
+                        else {
+                            *boundary = None;
+                            return;
+                        };
 
-                    let position = grid_idx.map(|i| i as T) * grid_node_size;
+                        let position = grid_idx.map(|i| i as T) * grid_node_size;
 
-                    let negative_normal =
-                        weighted_distance.normal * -weighted_distance.distance.signum();
+                        let negative_normal =
+                            weighted_distance.normal * -weighted_distance.distance.signum();
 
-                    *velocity = collider.conform_velocity(position, *velocity, negative_normal);
+                        *velocity = collider
+                            .conform_velocity(position, velocity.cast::<T>(), negative_normal)
+                            .cast::<A>();
 
-                    // TODO: this isn't needed for explicit integration
-                    let point_velocity = collider.kinematic.point_velocity_from_world(position);
-                    let collider_value = negative_normal.dot(&point_velocity);
-                    *boundary = Some(Boundary {
-                        normal: negative_normal,
-                        collider_value,
-                        condition_value: velocity.dot(&negative_normal) - collider_value,
-                        dual_variable: 1.,
+                        // TODO: this isn't needed for explicit integration
+                        let point_velocity = collider.kinematic.point_velocity_from_world(position);
+                        let negative_normal = negative_normal.cast::<A>();
+                        let collider_value = negative_normal.dot(&point_velocity.cast::<A>());
+                        *boundary = Some(Boundary {
+                            normal: negative_normal,
+                            collider_value,
+                            condition_value: velocity.dot(&negative_normal) - collider_value,
+                            dual_variable: 1.,
+                        });
                     });
-                });
-        }
+            });
 
         Ok(self)
     }
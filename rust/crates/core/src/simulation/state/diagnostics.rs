@@ -0,0 +1,183 @@
+// SPDX-License-Identifier: MIT
+//
+// Copyright 2025  Algebraic UG (haftungsbeschränkt)
+//
+// Use of this source code is governed by an MIT-style
+// license that can be found in the LICENSE_MIT file or at
+// https://opensource.org/licenses/MIT.
+
+use std::collections::BTreeMap;
+
+use blended_mpm_api::{A, T};
+use nalgebra::Vector3;
+use rayon::iter::{IndexedParallelIterator, IntoParallelRefIterator, ParallelIterator};
+use serde::{Deserialize, Serialize};
+
+use crate::math::flat::Flat3;
+
+use super::{ObjectIndex, State, attributes::AttributeDiagnostics, memory_usage::MemoryUsage};
+
+// Totals over the whole simulation, recorded for every stored frame.
+// Useful to see where energy, momentum or mass is gained or lost.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Diagnostics {
+    pub time: f64,
+    pub kinetic_energy: A,
+    pub elastic_energy: A,
+    pub linear_momentum: Vector3<A>,
+    pub angular_momentum: Vector3<A>,
+    pub mass: A,
+    pub free_grid_mass: A,
+    // conformed grids by collider name, separated grids by object name, objects
+    // on the free grid by the mass of their particles, which they scatter onto it
+    pub grid_masses: BTreeMap<String, A>,
+    #[serde(default)]
+    pub memory_usage: MemoryUsage,
+}
+
+impl State {
+    // `deterministic` sums in a single job, in the order of the particles.
+    pub fn diagnostics(&self, deterministic: bool) -> Diagnostics {
+        let ps = &self.particles;
+        let min_len = if deterministic { usize::MAX } else { 1 };
+
+        let kinetic_energy = ps
+            .masses
+            .par_iter()
+            .zip(&ps.velocities)
+            .with_min_len(min_len)
+            .map(|(mass, velocity)| 0.5 * *mass as A * velocity.cast::<A>().norm_squared())
+            .sum::<A>();
+        let elastic_energy = ps
+            .elastic_energies
+            .par_iter()
+            .zip(&ps.initial_volumes)
+            .with_min_len(min_len)
+            .map(|(elastic_energy, initial_volume)| (elastic_energy * initial_volume) as A)
+            .sum::<A>();
+        let linear_momentum = ps
+            .masses
+            .par_iter()
+            .zip(&ps.velocities)
+            .with_min_len(min_len)
+            .map(|(mass, velocity)| velocity.cast::<A>() * *mass as A)
+            .sum::<Vector3<A>>();
+        let angular_momentum = ps
+            .masses
+            .par_iter()
+            .zip(&ps.positions)
+            .zip(&ps.velocities)
+            .with_min_len(min_len)
+            .map(|((mass, position), velocity)| {
+                position.cast::<A>().cross(&velocity.cast::<A>()) * *mass as A
+            })
+            .sum::<Vector3<A>>();
+        let mass = ps
+            .masses
+            .par_iter()
+            .with_min_len(min_len)
+            .map(|mass| *mass as A)
+            .sum::<A>();
+
+        let grid_masses = self
+            .name_map
+            .iter()
+            .map(|(name, object_idx)| {
+                let grid_mass = match object_idx {
+                    ObjectIndex::Collider(idx) => {
+                        self.grid_collider_momentums[*idx].masses.iter().sum::<A>()
+                    }
+                    ObjectIndex::Solid(idx) => {
+                        let solid = &self.solid_objects[*idx];
+                        self.object_grid_mass(solid.velocity_field, &solid.particles)
+                    }
+                    ObjectIndex::Fluid(idx) => {
+                        let fluid = &self.fluid_objects[*idx];
+                        self.object_grid_mass(fluid.velocity_field, &fluid.particles)
+                    }
+                };
+                (name.clone(), grid_mass)
+            })
+            .collect();
+
+        Diagnostics {
+            time: self.time,
+            kinetic_energy,
+            elastic_energy,
+            linear_momentum,
+            angular_momentum,
+            mass,
+            free_grid_mass: self.grid_momentum.masses.iter().sum::<A>(),
+            grid_masses,
+            memory_usage: self.memory_usage(),
+        }
+    }
+
+    // on the free grid, the object's particles are summed in their order
+    fn object_grid_mass(&self, velocity_field: Option<usize>, particles: &[usize]) -> A {
+        match velocity_field {
+            Some(velocity_field) => self.grid_object_momentums[velocity_field]
+                .masses
+                .iter()
+                .sum::<A>(),
+            None => particles
+                .iter()
+                .map(|idx| self.particles.masses[self.particles.reverse_sort_map[*idx]] as A)
+                .sum::<A>(),
+        }
+    }
+}
+
+pub fn fetch_flat_diagnostics(
+    diagnostics: &[Diagnostics],
+    attribute: AttributeDiagnostics,
+) -> Vec<T> {
+    match attribute {
+        AttributeDiagnostics::Times => diagnostics.iter().map(|d| d.time as T).collect(),
+        AttributeDiagnostics::KineticEnergies => {
+            diagnostics.iter().map(|d| d.kinetic_energy as T).collect()
+        }
+        AttributeDiagnostics::ElasticEnergies => {
+            diagnostics.iter().map(|d| d.elastic_energy as T).collect()
+        }
+        AttributeDiagnostics::LinearMomenta => diagnostics
+            .iter()
+            .flat_map(|d| d.linear_momentum.cast::<T>().flat())
+            .collect(),
+        AttributeDiagnostics::AngularMomenta => diagnostics
+            .iter()
+            .flat_map(|d| d.angular_momentum.cast::<T>().flat())
+            .collect(),
+        AttributeDiagnostics::Masses => diagnostics.iter().map(|d| d.mass as T).collect(),
+        AttributeDiagnostics::FreeGridMasses => {
+            diagnostics.iter().map(|d| d.free_grid_mass as T).collect()
+        }
+        // bytes of particles, objects, collider distances, free, collider and object grids
+        AttributeDiagnostics::MemoryUsages => diagnostics
+            .iter()
+            .flat_map(|d| {
+                let MemoryUsage {
+                    particles,
+                    objects,
+                    grid_collider_distances,
+                    free_grid_momentum,
+                    grid_collider_momentums,
+                    grid_object_momentums,
+                } = d.memory_usage;
+                [
+                    particles,
+                    objects,
+                    grid_collider_distances,
+                    free_grid_momentum,
+                    grid_collider_momentums,
+                    grid_object_momentums,
+                ]
+                .map(|bytes| bytes as T)
+            })
+            .collect(),
+        AttributeDiagnostics::GridMasses(name) => diagnostics
+            .iter()
+            .map(|d| d.grid_masses.get(&name).cloned().unwrap_or(0.) as T)
+            .collect(),
+    }
+}
@@ -7,6 +7,7 @@
 // https://opensource.org/licenses/MIT.
 
 use anyhow::Result;
+use blended_mpm_api::A;
 use rayon::iter::{IntoParallelRefMutIterator, ParallelIterator};
 
 use super::{PhaseInput, State, profile};
@@ -14,13 +15,15 @@ use super::{PhaseInput, State, profile};
 impl State {
     pub(super) fn external_force(mut self, phase_input: PhaseInput) -> Result<Self> {
         profile!("external_force");
-        let time_step = phase_input.time_step;
-        let gravity = phase_input.setup.settings.gravity;
+        let time_step = phase_input.time_step as A;
+        let gravity = phase_input.setup.settings.gravity.cast::<A>();
+        // implicit in the drag to stay stable for any time step
+        let drag_factor = 1. / (1. + phase_input.setup.settings.air_drag as A * time_step);
         // TODO: try chaining
         for grid in self.grid_momentums_mut() {
             grid.velocities
                 .par_iter_mut()
-                .for_each(|velocity| *velocity += gravity * time_step);
+                .for_each(|velocity| *velocity = (*velocity + gravity * time_step) * drag_factor);
         }
 
         Ok(self)
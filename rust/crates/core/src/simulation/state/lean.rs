@@ -0,0 +1,151 @@
+// SPDX-License-Identifier: MIT
+//
+// Copyright 2025  Algebraic UG (haftungsbeschränkt)
+//
+// Use of this source code is governed by an MIT-style
+// license that can be found in the LICENSE_MIT file or at
+// https://opensource.org/licenses/MIT.
+
+use serde::{Deserialize, Serialize};
+use strum::EnumIter;
+
+use crate::simulation::{collider::Collider, particles::Particles};
+
+use super::{State, attributes::Attribute, columns::Column};
+
+// What lean frames keep besides the particle positions and the objects.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, EnumIter, Serialize, Deserialize)]
+pub enum LeanAttribute {
+    Masses,
+    Velocities,
+    PositionGradients,
+    ElasticEnergies,
+    ColliderInsides,
+    Grids,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FrameContents {
+    // a complete state that computing can resume from
+    #[default]
+    Checkpoint,
+    Lean(Vec<LeanAttribute>),
+    // only the header is left to stay within the disk budget
+    Evicted,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct FramePolicy {
+    // 1 stores every frame as a checkpoint
+    pub checkpoint_interval: usize,
+    pub lean_attributes: Vec<LeanAttribute>,
+}
+
+impl Default for FramePolicy {
+    fn default() -> Self {
+        Self {
+            checkpoint_interval: 1,
+            lean_attributes: Vec::new(),
+        }
+    }
+}
+
+impl FramePolicy {
+    // The last frame is a checkpoint to continue baking from.
+    pub fn contents(&self, frame: usize, number_of_frames: usize) -> FrameContents {
+        if self.checkpoint_interval <= 1
+            || frame.is_multiple_of(self.checkpoint_interval)
+            || frame + 1 == number_of_frames
+        {
+            FrameContents::Checkpoint
+        } else {
+            FrameContents::Lean(self.lean_attributes.clone())
+        }
+    }
+}
+
+impl FrameContents {
+    pub fn keeps(&self, lean_attribute: LeanAttribute) -> bool {
+        match self {
+            Self::Checkpoint => true,
+            Self::Lean(lean_attributes) => lean_attributes.contains(&lean_attribute),
+            Self::Evicted => false,
+        }
+    }
+
+    pub fn contains(&self, attribute: &Attribute) -> bool {
+        Column::for_attribute(attribute)
+            .into_iter()
+            .all(|column| column.is_stored(self))
+    }
+}
+
+impl State {
+    // The state as it's stored, lean frames only clone what they keep.
+    pub fn to_stored(&self, contents: &FrameContents) -> Self {
+        if *contents == FrameContents::Checkpoint {
+            return self.clone();
+        }
+        let keep = |lean_attribute| contents.keeps(lean_attribute);
+
+        let ps = &self.particles;
+        let particles = Particles {
+            sort_map: ps.sort_map.clone(),
+            reverse_sort_map: ps.reverse_sort_map.clone(),
+            positions: ps.positions.clone(),
+            masses: keep_or_empty(keep(LeanAttribute::Masses), &ps.masses),
+            initial_volumes: keep_or_empty(keep(LeanAttribute::Masses), &ps.initial_volumes),
+            velocities: keep_or_empty(keep(LeanAttribute::Velocities), &ps.velocities),
+            position_gradients: keep_or_empty(
+                keep(LeanAttribute::PositionGradients),
+                &ps.position_gradients,
+            ),
+            elastic_energies: keep_or_empty(
+                keep(LeanAttribute::ElasticEnergies),
+                &ps.elastic_energies,
+            ),
+            collider_insides: keep_or_empty(
+                keep(LeanAttribute::ColliderInsides),
+                &ps.collider_insides,
+            ),
+            ..Default::default()
+        };
+        let keep_grids = keep(LeanAttribute::Grids);
+
+        Self {
+            time: self.time,
+            phase: self.phase,
+            name_map: self.name_map.clone(),
+            particles,
+            solid_objects: self.solid_objects.clone(),
+            fluid_objects: self.fluid_objects.clone(),
+            // the scripted movements are in the setup
+            collider_objects: self
+                .collider_objects
+                .iter()
+                .map(|collider| Collider {
+                    surface_samples: collider.surface_samples.clone(),
+                    kinematic: collider.kinematic.clone(),
+                    scripted_movements: Vec::new(),
+                    ..*collider
+                })
+                .collect(),
+            grid_collider_distances: if keep_grids {
+                self.grid_collider_distances.clone()
+            } else {
+                Default::default()
+            },
+            grid_momentum: if keep_grids {
+                self.grid_momentum.clone()
+            } else {
+                Default::default()
+            },
+            grid_collider_momentums: keep_or_empty(keep_grids, &self.grid_collider_momentums),
+            grid_object_momentums: keep_or_empty(keep_grids, &self.grid_object_momentums),
+        }
+    }
+}
+
+fn keep_or_empty<E: Clone>(keep: bool, values: &[E]) -> Vec<E> {
+    if keep { values.to_vec() } else { Vec::new() }
+}
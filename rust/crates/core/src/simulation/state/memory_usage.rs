@@ -0,0 +1,155 @@
+// SPDX-License-Identifier: MIT
+//
+// Copyright 2025  Algebraic UG (haftungsbeschränkt)
+//
+// Use of this source code is governed by an MIT-style
+// license that can be found in the LICENSE_MIT file or at
+// https://opensource.org/licenses/MIT.
+
+use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
+use serde::{Deserialize, Serialize};
+
+use crate::simulation::{
+    grids::{GridColliderDistances, GridMomentum},
+    memory::HeapBytes,
+    particles::Particles,
+};
+
+use super::State;
+
+// Bytes used by the parts of a state, see `HeapBytes`.
+#[derive(Debug, Default, Clone, Copy, Serialize, Deserialize)]
+pub struct MemoryUsage {
+    pub particles: u64,
+    pub objects: u64,
+    pub grid_collider_distances: u64,
+    pub free_grid_momentum: u64,
+    pub grid_collider_momentums: u64,
+    pub grid_object_momentums: u64,
+}
+
+impl MemoryUsage {
+    pub fn total(&self) -> u64 {
+        self.particles
+            + self.objects
+            + self.grid_collider_distances
+            + self.free_grid_momentum
+            + self.grid_collider_momentums
+            + self.grid_object_momentums
+    }
+}
+
+impl State {
+    pub fn memory_usage(&self) -> MemoryUsage {
+        MemoryUsage {
+            particles: self.particles.heap_bytes(),
+            objects: self
+                .solid_objects
+                .iter()
+                .map(|solid| solid.particles.heap_bytes())
+                .chain(
+                    self.fluid_objects
+                        .iter()
+                        .map(|fluid| fluid.particles.heap_bytes()),
+                )
+                .chain(self.collider_objects.iter().map(|collider| {
+                    collider.surface_samples.heap_bytes() + collider.scripted_movements.heap_bytes()
+                }))
+                .sum(),
+            grid_collider_distances: self.grid_collider_distances.heap_bytes(),
+            free_grid_momentum: self.grid_momentum.heap_bytes(),
+            grid_collider_momentums: self
+                .grid_collider_momentums
+                .iter()
+                .map(HeapBytes::heap_bytes)
+                .sum(),
+            grid_object_momentums: self
+                .grid_object_momentums
+                .iter()
+                .map(HeapBytes::heap_bytes)
+                .sum(),
+        }
+    }
+}
+
+impl HeapBytes for Particles {
+    fn heap_bytes(&self) -> u64 {
+        let Self {
+            sort_map,
+            reverse_sort_map,
+            sort_keys,
+            stencil_weights,
+            parameters,
+            masses,
+            initial_volumes,
+            positions,
+            position_gradients,
+            velocities,
+            velocity_gradients,
+            elastic_energies,
+            collider_insides,
+            velocity_fields,
+            trial_position_gradients,
+            action_matrices,
+        } = self;
+        sort_map.heap_bytes()
+            + reverse_sort_map.heap_bytes()
+            + sort_keys.heap_bytes()
+            + stencil_weights.heap_bytes()
+            + parameters.heap_bytes()
+            + masses.heap_bytes()
+            + initial_volumes.heap_bytes()
+            + positions.heap_bytes()
+            + position_gradients.heap_bytes()
+            + velocities.heap_bytes()
+            + velocity_gradients.heap_bytes()
+            + elastic_energies.heap_bytes()
+            + collider_insides.heap_bytes()
+            + collider_insides
+                .par_iter()
+                .map(HeapBytes::heap_bytes)
+                .sum::<u64>()
+            + velocity_fields.heap_bytes()
+            + trial_position_gradients.heap_bytes()
+            + action_matrices.heap_bytes()
+    }
+}
+
+impl HeapBytes for GridMomentum {
+    fn heap_bytes(&self) -> u64 {
+        let Self {
+            map,
+            masses,
+            velocities,
+            mass_gradients,
+            reference_velocities,
+            newton_direction,
+            boundaries,
+            residual,
+            cg_direction,
+            cg_conjugated,
+        } = self;
+        map.heap_bytes()
+            + masses.heap_bytes()
+            + velocities.heap_bytes()
+            + mass_gradients.heap_bytes()
+            + reference_velocities.heap_bytes()
+            + newton_direction.heap_bytes()
+            + boundaries.heap_bytes()
+            + residual.heap_bytes()
+            + cg_direction.heap_bytes()
+            + cg_conjugated.heap_bytes()
+    }
+}
+
+impl HeapBytes for GridColliderDistances {
+    fn heap_bytes(&self) -> u64 {
+        self.map.heap_bytes()
+            + self.nodes.heap_bytes()
+            + self
+                .nodes
+                .par_iter()
+                .map(|node| node.weighted_distances.heap_bytes())
+                .sum::<u64>()
+    }
+}
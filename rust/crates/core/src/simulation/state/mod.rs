@@ -10,6 +10,7 @@ use anyhow::{Context, Result, bail, ensure};
 use blended_mpm_api::T;
 use fxhash::FxHashMap;
 use nalgebra::Vector3;
+use rayon::iter::{IndexedParallelIterator, IntoParallelRefIterator, ParallelIterator};
 use serde::{Deserialize, Serialize};
 use std::{
     collections::BTreeMap,
@@ -50,16 +51,24 @@ pub(crate) use profile;
 
 mod advect_particles;
 pub(super) mod attributes;
+mod baseline;
 mod collect_insides;
 mod collect_velocity;
+pub(super) mod columns;
 mod conform_to_colliders;
+pub(super) mod diagnostics;
 mod external_force;
 mod implicit_solve;
+pub(super) mod lean;
+pub(super) mod memory_usage;
 mod move_collider;
-mod register_contributors;
+mod object_contact;
 mod scatter_collider_distances;
 mod scatter_momentum;
 mod sort;
+#[cfg(test)]
+pub(super) mod tests;
+mod transfer;
 mod update_momentum_maps;
 
 #[derive(Clone, Serialize, Deserialize)]
@@ -78,6 +87,7 @@ pub struct State {
 
     grid_momentum: GridMomentum,
     grid_collider_momentums: Vec<GridMomentum>,
+    grid_object_momentums: Vec<GridMomentum>,
 }
 
 #[derive(Clone)]
@@ -97,10 +107,10 @@ pub enum Phase {
     ScatterColliderDistances,
     CollectInsides,
     UpdateMomentumMaps,
-    RegisterContributors,
     ScatterMomentum,
     ScatterMomentumExplicit,
     ExternalForce,
+    ObjectContact,
     ConformToColliders,
     ImplicitSolve,
     CollectVelocity,
@@ -115,10 +125,10 @@ impl Phase {
             Self::ScatterColliderDistances => State::scatter_collider_distances,
             Self::CollectInsides => State::collect_insides,
             Self::UpdateMomentumMaps => State::update_momentum_maps,
-            Self::RegisterContributors => State::register_contributors,
             Self::ScatterMomentum => State::scatter_momentum::<false>,
             Self::ScatterMomentumExplicit => State::scatter_momentum::<true>,
             Self::ExternalForce => State::external_force,
+            Self::ObjectContact => State::object_contact,
             Self::ConformToColliders => State::conform_to_colliders,
             Self::ImplicitSolve => State::implicit_solve,
             Self::CollectVelocity => State::collect_velocity,
@@ -153,84 +163,142 @@ impl State {
             steps_to_completion: NonZero::new(objects.len().max(1)).unwrap(),
         });
 
+        // Handed out in order, before the objects are built in parallel.
+        let mut number_of_velocity_fields = 0;
+        let velocity_fields: Vec<Option<usize>> = objects
+            .iter()
+            .map(|ObjectWithData { object, .. }| {
+                let separate = match &object.settings {
+                    ObjectSettings::Solid(object_settings) => {
+                        object_settings.separate_velocity_field
+                    }
+                    ObjectSettings::Fluid(object_settings) => {
+                        object_settings.separate_velocity_field
+                    }
+                    ObjectSettings::Collider(_) => false,
+                };
+                separate.then(|| {
+                    number_of_velocity_fields += 1;
+                    number_of_velocity_fields - 1
+                })
+            })
+            .collect();
+
+        // Solids and fluids get their own particles, merged in order afterwards.
+        enum Built {
+            Solid(Solid, Particles),
+            Fluid(Fluid, Particles),
+            Collider(Collider),
+        }
+
+        let built = objects
+            .par_iter()
+            .zip(velocity_fields)
+            .map(
+                |(
+                    ObjectWithData {
+                        object,
+                        mesh,
+                        scripted_frames,
+                    },
+                    velocity_field,
+                )|
+                 -> Result<(String, Built)> {
+                    ensure!(run.load(Ordering::Relaxed), "Cancelled");
+
+                    let name = object.name.clone();
+                    info!(name, "object");
+
+                    if object.scale.iter().any(|c| *c < 0.) {
+                        bail!("negative scaling isn't supported, please check '{name}'");
+                    }
+
+                    let kinematic = object
+                        .clone()
+                        .try_into()
+                        .context("Kinematic construction")?;
+                    let built = match &object.settings {
+                        ObjectSettings::Solid(object_settings) => {
+                            let mut particles = Particles::default();
+                            let solid = Solid::new(SolidConstruction {
+                                name: &name,
+                                run: run.clone(),
+                                report: report.clone(),
+                                settings,
+                                kinematic,
+                                object_settings: object_settings.clone(),
+                                mesh,
+                                particles: &mut particles,
+                                velocity_field,
+                            })
+                            .with_context(|| format!("Solid creation: '{name}'"))?;
+                            Built::Solid(solid, particles)
+                        }
+                        ObjectSettings::Fluid(object_settings) => {
+                            let mut particles = Particles::default();
+                            let fluid = Fluid::new(FluidConstruction {
+                                name: &name,
+                                run: run.clone(),
+                                report: report.clone(),
+                                settings,
+                                kinematic,
+                                object_settings: object_settings.clone(),
+                                mesh,
+                                particles: &mut particles,
+                                velocity_field,
+                            })
+                            .with_context(|| format!("Fluid creation: '{name}'"))?;
+                            Built::Fluid(fluid, particles)
+                        }
+                        ObjectSettings::Collider(object_settings) => {
+                            let collider = Collider::new(ColliderConstruction {
+                                name: &name,
+                                run: run.clone(),
+                                report: report.clone(),
+                                settings,
+                                kinematic,
+                                object_settings: object_settings.clone(),
+                                mesh,
+                                scripted_frames: scripted_frames.clone(),
+                            })
+                            .with_context(|| format!("Collider creation: '{name}'"))?;
+                            Built::Collider(collider)
+                        }
+                    };
+                    report.step();
+                    Ok((name, built))
+                },
+            )
+            .collect::<Result<Vec<_>>>()?;
+
         let mut name_map = BTreeMap::new();
         let mut particles = Particles::default();
         let mut solid_objects = Vec::new();
         let mut fluid_objects = Vec::new();
         let mut collider_objects = Vec::new();
-        for ObjectWithData {
-            object,
-            mesh,
-            scripted_frames,
-        } in objects
-        {
-            ensure!(run.load(Ordering::Relaxed), "Cancelled");
-
-            let name = object.name.clone();
-            info!(name, "object");
-
-            if object.scale.iter().any(|c| *c < 0.) {
-                bail!("negative scaling isn't supported, please check '{name}'");
-            }
-
-            let kinematic = object
-                .clone()
-                .try_into()
-                .context("Kinematic construction")?;
-            let object_idx = match &object.settings {
-                ObjectSettings::Solid(object_settings) => {
-                    let solid = Solid::new(SolidConstruction {
-                        name: &name,
-                        run: run.clone(),
-                        report: report.clone(),
-                        settings,
-                        kinematic,
-                        object_settings: object_settings.clone(),
-                        mesh,
-                        particles: &mut particles,
-                    })
-                    .with_context(|| format!("Solid creation: '{name}'"))?;
-                    let object_idx = ObjectIndex::Solid(solid_objects.len());
+        for (name, built) in built {
+            let object_idx = match built {
+                Built::Solid(mut solid, solid_particles) => {
+                    let offset = particles.append(solid_particles);
+                    solid.particles.iter_mut().for_each(|idx| *idx += offset);
                     solid_objects.push(solid);
-                    object_idx
+                    ObjectIndex::Solid(solid_objects.len() - 1)
                 }
-                ObjectSettings::Fluid(object_settings) => {
-                    let fluid = Fluid::new(FluidConstruction {
-                        name: &name,
-                        run: run.clone(),
-                        report: report.clone(),
-                        settings,
-                        kinematic,
-                        object_settings: object_settings.clone(),
-                        mesh,
-                        particles: &mut particles,
-                    })
-                    .with_context(|| format!("Fluid creation: '{name}'"))?;
-                    let object_idx = ObjectIndex::Fluid(fluid_objects.len());
+                Built::Fluid(mut fluid, fluid_particles) => {
+                    let offset = particles.append(fluid_particles);
+                    fluid.particles.iter_mut().for_each(|idx| *idx += offset);
                     fluid_objects.push(fluid);
-                    object_idx
+                    ObjectIndex::Fluid(fluid_objects.len() - 1)
                 }
-                ObjectSettings::Collider(object_settings) => {
-                    let collider = Collider::new(ColliderConstruction {
-                        name: &name,
-                        run: run.clone(),
-                        report: report.clone(),
-                        settings,
-                        kinematic,
-                        object_settings: object_settings.clone(),
-                        mesh,
-                        scripted_frames: scripted_frames.clone(),
-                    })
-                    .with_context(|| format!("Collider creation: '{name}'"))?;
-                    let object_idx = ObjectIndex::Collider(collider_objects.len());
+                Built::Collider(collider) => {
                     collider_objects.push(collider);
-                    object_idx
+                    ObjectIndex::Collider(collider_objects.len() - 1)
                 }
             };
             ensure!(name_map.insert(name, object_idx).is_none());
-            report.step();
         }
         let grid_collider_momentums = vec![Default::default(); collider_objects.len()];
+        let grid_object_momentums = vec![Default::default(); number_of_velocity_fields];
 
         info!(
             solid_objects = solid_objects.len(),
@@ -258,6 +326,7 @@ impl State {
             grid_collider_distances: Default::default(),
             grid_momentum: Default::default(),
             grid_collider_momentums,
+            grid_object_momentums,
         })
     }
 
@@ -319,8 +388,28 @@ impl State {
         self.phase
     }
 
+    pub fn number_of_particles(&self) -> usize {
+        self.particles.positions.len()
+    }
+
+    fn grid_momentums(&self) -> impl Iterator<Item = &GridMomentum> {
+        once(&self.grid_momentum)
+            .chain(self.grid_collider_momentums.iter())
+            .chain(self.grid_object_momentums.iter())
+    }
+
     fn grid_momentums_mut(&mut self) -> impl Iterator<Item = &mut GridMomentum> {
-        once(&mut self.grid_momentum).chain(self.grid_collider_momentums.iter_mut())
+        once(&mut self.grid_momentum)
+            .chain(self.grid_collider_momentums.iter_mut())
+            .chain(self.grid_object_momentums.iter_mut())
+    }
+
+    fn velocity_field(&self, object_idx: &ObjectIndex) -> Option<usize> {
+        match object_idx {
+            ObjectIndex::Solid(idx) => self.solid_objects[*idx].velocity_field,
+            ObjectIndex::Fluid(idx) => self.fluid_objects[*idx].velocity_field,
+            ObjectIndex::Collider(_) => None,
+        }
     }
 }
 
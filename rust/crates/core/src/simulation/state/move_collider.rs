@@ -8,9 +8,15 @@
 
 use anyhow::{Context, Result};
 
-use crate::simulation::kinematic::{Kinematic, ScriptedMovement};
+use crate::{
+    api::{ObjectWithData, Setup},
+    simulation::{
+        collider::scripted_movements,
+        kinematic::{Kinematic, ScriptedMovement},
+    },
+};
 
-use super::{PhaseInput, State, profile};
+use super::{ObjectIndex, PhaseInput, State, profile};
 
 impl State {
     pub(super) fn move_collider(mut self, _: PhaseInput) -> Result<Self> {
@@ -29,4 +35,22 @@ impl State {
         }
         Ok(self)
     }
+
+    // Checkpoints keep the scripted movements they were computed with,
+    // continuing after the animation of a collider changed needs the new ones.
+    pub fn update_scripted_movements(&mut self, setup: &Setup) -> Result<()> {
+        for ObjectWithData {
+            object,
+            scripted_frames,
+            ..
+        } in &setup.objects
+        {
+            if let Some(ObjectIndex::Collider(idx)) = self.name_map.get(&object.name) {
+                self.collider_objects[*idx].scripted_movements =
+                    scripted_movements(scripted_frames, setup.settings.frames_per_second)
+                        .with_context(|| format!("Scripted movements of '{}'", object.name))?;
+            }
+        }
+        Ok(())
+    }
 }
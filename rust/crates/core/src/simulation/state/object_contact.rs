@@ -0,0 +1,160 @@
+// SPDX-License-Identifier: MIT
+//
+// Copyright 2025  Algebraic UG (haftungsbeschränkt)
+//
+// Use of this source code is governed by an MIT-style
+// license that can be found in the LICENSE_MIT file or at
+// https://opensource.org/licenses/MIT.
+
+use std::iter::once;
+
+use anyhow::Result;
+use blended_mpm_api::A;
+use fxhash::FxHashMap;
+use nalgebra::Vector3;
+use rayon::iter::{IntoParallelIterator, IntoParallelRefIterator, ParallelIterator};
+
+use crate::{math::NORMALIZATION_EPS, simulation::grids::SharedSlice};
+
+use super::{PhaseInput, State, profile, transfer::TransferTarget};
+
+impl State {
+    // Objects with a separate velocity field only meet on the grid nodes they share.
+    // There, each field that approaches the center of mass velocity loses the normal part,
+    // while the tangential part is subject to friction.
+    // Separating fields are left alone s.t. objects can come apart again.
+    pub(super) fn object_contact(mut self, phase_input: PhaseInput) -> Result<Self> {
+        profile!("object_contact");
+        if self.grid_object_momentums.is_empty() {
+            return Ok(self);
+        }
+
+        let grid_node_size = phase_input.setup.settings.grid_node_size;
+        let friction_factor = phase_input.setup.settings.contact_friction_factor as A;
+        self.prepare_stencil_weights(grid_node_size);
+
+        {
+            profile!("mass gradients");
+            let mut mass_gradients = self
+                .grid_momentums()
+                .map(|grid| vec![Vector3::zeros(); grid.map.len()])
+                .collect::<Vec<_>>();
+            {
+                let shared_mass_gradients = mass_gradients
+                    .iter_mut()
+                    .map(|mass_gradients| SharedSlice::new(mass_gradients))
+                    .collect::<Vec<_>>();
+                self.par_for_each_particle_colored(|particle_idx| {
+                    let stencil = &self.particles.stencil_weights[particle_idx];
+                    let mass = self.particles.masses[particle_idx];
+                    for TransferTarget { offset, grid, node } in self.transfer_targets(particle_idx)
+                    {
+                        let mass_gradient =
+                            (stencil.weight_gradient(offset) * (mass / grid_node_size)).cast::<A>();
+
+                        // SAFETY: the coloring guarantees that no other thread touches this node
+                        unsafe {
+                            *shared_mass_gradients[grid].get_mut(node) += mass_gradient;
+                        }
+                    }
+                });
+            }
+            self.grid_momentums_mut()
+                .zip(mass_gradients)
+                .for_each(|(grid, mass_gradients)| grid.mass_gradients = mass_gradients);
+        }
+
+        // Field 0 is the common grid, the others are offset by one.
+        let shared_nodes: Vec<Vec<(usize, usize)>> = {
+            profile!("detect shared nodes");
+            let mut nodes: FxHashMap<Vector3<i32>, Vec<(usize, usize)>> = Default::default();
+            for (field_idx, grid) in self.grid_object_momentums.iter().enumerate() {
+                for (grid_idx, idx) in grid.map.iter() {
+                    // skip the unused nodes of a block
+                    if grid.masses[idx] > 0. {
+                        nodes
+                            .entry(grid_idx)
+                            .or_default()
+                            .push((field_idx + 1, idx));
+                    }
+                }
+            }
+            nodes
+                .into_par_iter()
+                .filter_map(|(grid_idx, mut fields)| {
+                    if let Some(idx) = self.grid_momentum.map.get(&grid_idx) {
+                        fields.push((0, idx));
+                    }
+                    (fields.len() > 1).then_some(fields)
+                })
+                .collect()
+        };
+
+        let corrected_velocities: Vec<(usize, usize, Vector3<A>)> = {
+            profile!("resolve");
+            let fields = once(&self.grid_momentum)
+                .chain(self.grid_object_momentums.iter())
+                .collect::<Vec<_>>();
+            let fields = &fields;
+            shared_nodes
+                .par_iter()
+                .flat_map_iter(|node_fields| {
+                    let (mass, momentum) = node_fields.iter().fold(
+                        (0., Vector3::zeros()),
+                        |(mass, momentum), &(field_idx, idx)| {
+                            let grid = fields[field_idx];
+                            (
+                                mass + grid.masses[idx],
+                                momentum + grid.velocities[idx] * grid.masses[idx],
+                            )
+                        },
+                    );
+                    let center_velocity = if mass > 0. {
+                        momentum / mass
+                    } else {
+                        Vector3::zeros()
+                    };
+
+                    node_fields.iter().filter_map(move |&(field_idx, idx)| {
+                        let grid = fields[field_idx];
+                        if grid.masses[idx] <= 0. {
+                            return None;
+                        }
+
+                        // pointing out of the object, away from its mass
+                        let normal =
+                            (-grid.mass_gradients[idx]).try_normalize(NORMALIZATION_EPS as A)?;
+
+                        let relative_velocity = grid.velocities[idx] - center_velocity;
+                        let normal_part = normal.dot(&relative_velocity);
+                        if normal_part <= 0. {
+                            return None;
+                        }
+
+                        let tangent_velocity = relative_velocity - normal * normal_part;
+                        let tangent_part = tangent_velocity.norm();
+                        let tangent_velocity = if tangent_part == 0. {
+                            Vector3::zeros()
+                        } else {
+                            tangent_velocity
+                                * (1. - friction_factor * normal_part / tangent_part).max(0.)
+                        };
+
+                        Some((field_idx, idx, center_velocity + tangent_velocity))
+                    })
+                })
+                .collect()
+        };
+
+        for (field_idx, idx, velocity) in corrected_velocities {
+            let grid = if field_idx == 0 {
+                &mut self.grid_momentum
+            } else {
+                &mut self.grid_object_momentums[field_idx - 1]
+            };
+            grid.velocities[idx] = velocity;
+        }
+
+        Ok(self)
+    }
+}
@@ -8,14 +8,15 @@
 
 use anyhow::Result;
 use blended_mpm_api::T;
+use fxhash::FxHashSet;
 use nalgebra::Vector3;
 use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
-use std::collections::hash_map::Entry;
+use std::{collections::hash_map::Entry, mem::take};
 
 use crate::{
     api::SurfaceSample,
     math::SURFACE_DISK_SIZE_FACTOR,
-    simulation::grids::WeightedDistance,
+    simulation::grids::{BLOCK_BITS, GridNodeColliderDistances, Mutex, WeightedDistance, block_of},
     weights::{kernel_quadratic_unrolled, position_to_shift_quadratic},
 };
 
@@ -28,16 +29,58 @@ impl State {
     pub(super) fn scatter_collider_distances(mut self, phase_input: PhaseInput) -> Result<Self> {
         profile!("scatter_collider_distances");
         let grid_node_size = phase_input.setup.settings.grid_node_size;
+        self.scatter_collider_distances_cull(grid_node_size);
         self.scatter_collider_distances_create_entries(grid_node_size);
         self.scatter_collider_distances_reset();
         self.scatter_collider_distances_scatter(grid_node_size);
         Ok(self)
     }
 
+    // Colliders that aren't close to any particle can't interact with them.
+    fn scatter_collider_distances_cull(&mut self, grid_node_size: T) {
+        profile!("cull");
+        let particle_blocks: FxHashSet<Vector3<i32>> = self
+            .particles
+            .positions
+            .par_iter()
+            .map(|position| block_of(position_to_shift_quadratic(position, grid_node_size)))
+            .collect();
+
+        for collider in &mut self.collider_objects {
+            let center = collider
+                .kinematic
+                .to_world_position(collider.bounding_center);
+            let radius = Vector3::repeat(collider.bounding_radius);
+
+            // nodes the surface samples can reach
+            let min = ((center - radius) / grid_node_size).map(|x| x.floor() as i32 - 2);
+            let max = ((center + radius) / grid_node_size).map(|x| x.ceil() as i32 + 2);
+
+            // blocks with particles that can reach those nodes,
+            // the stencils starting in a block span six nodes
+            let min = (min - Vector3::repeat(5)).map(|i| i >> BLOCK_BITS);
+            let max = max.map(|i| i >> BLOCK_BITS);
+
+            let was_culled = collider.culled;
+            collider.culled = !particle_blocks.iter().any(|block| {
+                block
+                    .iter()
+                    .zip(&min)
+                    .zip(&max)
+                    .all(|((i, min), max)| min <= i && i <= max)
+            });
+
+            // the entries might be missing
+            if was_culled && !collider.culled {
+                collider.has_moved = true;
+            }
+        }
+    }
+
     fn scatter_collider_distances_create_entries(&mut self, grid_node_size: T) {
         profile!("create_entries");
         for collider in &self.collider_objects {
-            if !collider.has_moved {
+            if !collider.has_moved || collider.culled {
                 continue;
             }
             let new_entries: Vec<Vector3<i32>> = collider
@@ -55,11 +98,7 @@ impl State {
                         .filter(|grid_idx| !self.grid_collider_distances.contains_key(grid_idx))
                 })
                 .collect();
-            self.grid_collider_distances.extend(
-                new_entries
-                    .into_iter()
-                    .map(|grid_idx| (grid_idx, Default::default())),
-            );
+            self.grid_collider_distances.extend(new_entries);
         }
     }
 
@@ -67,13 +106,23 @@ impl State {
         profile!("reset");
         self.grid_collider_distances
             .values_mut()
-            .for_each(|node| node.get_mut().unwrap().weighted_distances.clear());
+            .for_each(|node| node.weighted_distances.clear());
     }
 
     // Splat distance information by projecting oriented disks.
-    fn scatter_collider_distances_scatter(&self, grid_node_size: T) {
+    // Disks of a collider overlap, so the nodes are only locked while splatting.
+    fn scatter_collider_distances_scatter(&mut self, grid_node_size: T) {
         profile!("scatter");
+        let nodes: Vec<Mutex<GridNodeColliderDistances>> =
+            take(&mut self.grid_collider_distances.nodes)
+                .into_iter()
+                .map(Mutex::from)
+                .collect();
+        let map = &self.grid_collider_distances.map;
         for (collider_idx, collider) in self.collider_objects.iter().enumerate() {
+            if collider.culled {
+                continue;
+            }
             collider
                 .surface_samples
                 .par_iter()
@@ -82,8 +131,9 @@ impl State {
                     let normal = collider.kinematic.to_world_normal(*normal);
 
                     let shift = position_to_shift_quadratic(&position, grid_node_size);
+                    let stencil = map.stencil(shift);
 
-                    kernel_quadratic_unrolled!(move |grid_idx: Vector3<i32>| {
+                    kernel_quadratic_unrolled!(|grid_idx: Vector3<i32>| {
                         let grid_idx = grid_idx + shift;
                         let grid_node_position = grid_idx.map(|i| i as T) * grid_node_size;
                         let to_grid_node = grid_node_position - position;
@@ -93,11 +143,8 @@ impl State {
                             // trust that another nearby disk will be a better fit
                             return;
                         }
-                        let mut grid_node = self
-                            .grid_collider_distances
-                            .get(&grid_idx)
-                            .expect("missing node")
-                            .lock();
+                        let mut grid_node =
+                            nodes[stencil.get(&grid_idx).expect("missing node")].lock();
                         match grid_node.weighted_distances.entry(collider_idx) {
                             Entry::Occupied(mut occupied_entry) => {
                                 if distance.abs() < occupied_entry.get().distance.abs() {
@@ -112,5 +159,6 @@ impl State {
                     });
                 });
         }
+        self.grid_collider_distances.nodes = nodes.into_iter().map(Mutex::into_inner).collect();
     }
 }
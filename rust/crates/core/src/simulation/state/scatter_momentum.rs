@@ -6,22 +6,18 @@
 // license that can be found in the LICENSE_MIT file or at
 // https://opensource.org/licenses/MIT.
 
-use std::mem::take;
-
 use anyhow::Result;
-use blended_mpm_api::T;
-use nalgebra::Vector3;
-use rayon::iter::{IndexedParallelIterator, IntoParallelIterator, ParallelIterator};
-
-use crate::{
-    simulation::{
-        elastic::{first_piola_stress_inviscid, first_piola_stress_neo_hookean},
-        particles::ParticleParameters,
-    },
-    weights::kernel_quadratic,
+use blended_mpm_api::{A, T};
+use nalgebra::{Matrix3, Vector3};
+use rayon::iter::{IndexedParallelIterator, IntoParallelRefMutIterator, ParallelIterator};
+
+use crate::simulation::{
+    elastic::{first_piola_stress_inviscid, first_piola_stress_neo_hookean},
+    grids::SharedSlice,
+    particles::ParticleParameters,
 };
 
-use super::{PhaseInput, State, profile};
+use super::{PhaseInput, State, profile, transfer::TransferTarget};
 
 impl State {
     // Mass and velocity transported by particles is scattered to the grids.
@@ -33,93 +29,114 @@ impl State {
         profile!("scatter_momentum");
         let grid_node_size = phase_input.setup.settings.grid_node_size;
         let scaling = phase_input.time_step * 4. / grid_node_size.powi(2);
+        self.prepare_stencil_weights(grid_node_size);
+
+        // Separate memory to satisfy the borrow checker, moved into the grids at the end
+        let (mut masses, mut momentums): (Vec<Vec<A>>, Vec<Vec<Vector3<A>>>) = self
+            .grid_momentums()
+            .map(|grid| {
+                (
+                    vec![0.; grid.map.len()],
+                    vec![Vector3::zeros(); grid.map.len()],
+                )
+            })
+            .unzip();
+
+        {
+            let shared_masses = masses
+                .iter_mut()
+                .map(|masses| SharedSlice::new(masses))
+                .collect::<Vec<_>>();
+            let shared_momentums = momentums
+                .iter_mut()
+                .map(|momentums| SharedSlice::new(momentums))
+                .collect::<Vec<_>>();
+
+            self.par_for_each_particle_colored(|particle_idx| {
+                let stencil = &self.particles.stencil_weights[particle_idx];
+                let mass = self.particles.masses[particle_idx];
+                let velocity = self.particles.velocities[particle_idx];
+                let velocity_gradient = &self.particles.velocity_gradients[particle_idx];
+
+                // maps the offset to the grid node onto the imparted force
+                let force_matrix = if EXPLICIT_FORCES {
+                    let position_gradient = &self.particles.position_gradients[particle_idx];
+                    let initial_volume = self.particles.initial_volumes[particle_idx];
+                    let common_viscosity;
+                    let bulk_viscosity;
+                    let stress = match self.particles.parameters[particle_idx] {
+                        ParticleParameters::Solid {
+                            mu,
+                            lambda,
+                            viscosity,
+                            stiffness_damping,
+                            ..
+                        } => {
+                            common_viscosity = viscosity + stiffness_damping * mu;
+                            bulk_viscosity = stiffness_damping * lambda;
+                            first_piola_stress_neo_hookean(mu, lambda, position_gradient)
+                        }
+                        ParticleParameters::Fluid {
+                            exponent,
+                            bulk_modulus,
+                            viscosity,
+                            stiffness_damping,
+                            ..
+                        } => {
+                            common_viscosity = viscosity;
+                            bulk_viscosity = stiffness_damping * bulk_modulus;
+                            first_piola_stress_inviscid(bulk_modulus, exponent, position_gradient)
+                        }
+                    };
 
-        // Take memory to satisfy the borrow checker, return at the end
-        let mut grids = self.grid_momentums_mut().map(take).collect::<Vec<_>>();
-        for grid in &mut grids {
-            grid.masses = vec![0.; grid.map.len()];
-            grid.velocities = vec![Vector3::zeros(); grid.map.len()];
-            let keys = grid.map.keys().collect::<Vec<_>>();
-            keys.into_par_iter()
-                .zip(&mut grid.contributors)
-                .zip(&mut grid.masses)
-                .zip(&mut grid.velocities)
-                .for_each(|(((grid_idx, contributors), mass), velocity)| {
-                    for &particle_idx in contributors.get_mut().unwrap().iter() {
-                        let normalized = self.particles.positions[particle_idx] / grid_node_size;
-
-                        let to_grid_node_normalized = grid_idx.map(|x| x as T) - normalized;
-                        let weight = to_grid_node_normalized.map(kernel_quadratic).product();
-
-                        let to_grid_node = to_grid_node_normalized * grid_node_size;
-
-                        let mut imparted_momentum = (self.particles.velocities[particle_idx]
-                            + self.particles.velocity_gradients[particle_idx] * to_grid_node)
-                            * self.particles.masses[particle_idx];
-
-                        if EXPLICIT_FORCES {
-                            let position_gradient =
-                                &self.particles.position_gradients[particle_idx];
-                            let common_viscosity;
-                            let stress = match self.particles.parameters[particle_idx] {
-                                ParticleParameters::Solid {
-                                    mu,
-                                    lambda,
-                                    viscosity,
-                                } => {
-                                    common_viscosity = viscosity;
-                                    first_piola_stress_neo_hookean(mu, lambda, position_gradient)
-                                }
-                                ParticleParameters::Fluid {
-                                    exponent,
-                                    bulk_modulus,
-                                    viscosity,
-                                } => {
-                                    common_viscosity = viscosity;
-                                    first_piola_stress_inviscid(
-                                        bulk_modulus,
-                                        exponent,
-                                        position_gradient,
-                                    )
-                                }
-                            };
+                    let strain_rate =
+                        (velocity_gradient + velocity_gradient.transpose()).scale(0.5);
+                    let cauchy_stress = 2. * common_viscosity * strain_rate
+                        + Matrix3::from_diagonal_element(bulk_viscosity * strain_rate.trace());
 
-                            let velocity_gradient =
-                                &self.particles.velocity_gradients[particle_idx];
-                            let strain_rate =
-                                (velocity_gradient + velocity_gradient.transpose()).scale(0.5);
-                            let cauchy_stress = 2. * common_viscosity * strain_rate;
+                    cauchy_stress * (scaling * position_gradient.determinant() * initial_volume)
+                        + stress * position_gradient.transpose() * (scaling * initial_volume)
+                } else {
+                    Matrix3::zeros()
+                };
 
-                            imparted_momentum -= cauchy_stress
-                                * (to_grid_node
-                                    * (scaling
-                                        * position_gradient.determinant()
-                                        * self.particles.initial_volumes[particle_idx]));
+                for TransferTarget { offset, grid, node } in self.transfer_targets(particle_idx) {
+                    let weight = stencil.weight(offset);
+                    let to_grid_node = (offset.map(|x| x as T) - stencil.shifted) * grid_node_size;
 
-                            imparted_momentum -= stress
-                                * (position_gradient.transpose()
-                                    * (to_grid_node
-                                        * (scaling
-                                            * self.particles.initial_volumes[particle_idx])));
-                        }
+                    let mut imparted_momentum =
+                        (velocity + velocity_gradient * to_grid_node) * mass;
 
-                        imparted_momentum *= weight;
+                    if EXPLICIT_FORCES {
+                        imparted_momentum -= force_matrix * to_grid_node;
+                    }
 
-                        *mass += weight * self.particles.masses[particle_idx];
-                        *velocity += imparted_momentum;
+                    // SAFETY: the coloring guarantees that no other thread touches this node
+                    unsafe {
+                        *shared_masses[grid].get_mut(node) += (weight * mass) as A;
+                        *shared_momentums[grid].get_mut(node) +=
+                            (imparted_momentum * weight).cast::<A>();
                     }
+                }
+            });
+        }
 
+        for ((grid, masses), mut velocities) in self.grid_momentums_mut().zip(masses).zip(momentums)
+        {
+            velocities
+                .par_iter_mut()
+                .zip(&masses)
+                .for_each(|(velocity, mass)| {
                     if *mass > 0. {
                         *velocity /= *mass;
                     } else {
-                        // Numerical edge case
+                        // Numerical edge case, or an unused node of a block
                         *velocity = Vector3::zeros();
                     }
                 });
+            grid.masses = masses;
+            grid.velocities = velocities;
         }
-        self.grid_momentums_mut()
-            .zip(grids)
-            .for_each(|(old, new)| *old = new);
         Ok(self)
     }
 }
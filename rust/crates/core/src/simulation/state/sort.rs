@@ -9,54 +9,61 @@
 use anyhow::Result;
 use blended_mpm_api::T;
 use nalgebra::Vector3;
-use rayon::{Scope, scope, slice::ParallelSliceMut};
+use rayon::{
+    Scope,
+    iter::{IndexedParallelIterator, IntoParallelRefIterator, ParallelIterator},
+    scope,
+    slice::ParallelSliceMut,
+};
 
-use crate::simulation::particles::Particles;
+use crate::{simulation::particles::Particles, weights::position_to_shift_quadratic};
 
 use super::{PhaseInput, State, profile};
 
+// Re-sort once more than 1 / RESORT_FRACTION of the particles changed their cell.
+const RESORT_FRACTION: usize = 100;
+
 impl State {
     // This is only to optimize memory access.
+    // The scatter also benefits from the particles of a block being contiguous.
     pub(super) fn sort(mut self, phase_input: PhaseInput) -> Result<Self> {
         profile!("sort");
         let grid_node_size = phase_input.setup.settings.grid_node_size;
-
-        // Probably many other alternatives exist, e.g. one could do a z-order curve.
-        // This seemed to be faster though. Maybe try again with cached keys?
-        #[derive(Ord, PartialOrd, PartialEq, Eq, Clone, Copy)]
-        struct SortingPos {
-            i: i32,
-            j: i32,
-            k: i32,
-        }
-
-        let to_sorting_pos = |position: &Vector3<T>| SortingPos {
-            i: (position.x / grid_node_size - 0.5).floor() as i32,
-            j: (position.y / grid_node_size - 0.5).floor() as i32,
-            k: (position.z / grid_node_size - 0.5).floor() as i32,
+        let to_sorting_key = |position: &Vector3<T>| {
+            morton_key(position_to_shift_quadratic(position, grid_node_size))
         };
 
-        {
+        'simulated: {
             profile!("simulated particles");
 
-            let mut tmp: Vec<(usize, SortingPos)> = {
-                profile!("create index-position-pairs");
+            let mut keys: Vec<u64> = {
+                profile!("compute keys");
                 self.particles
                     .positions
-                    .iter()
-                    .map(to_sorting_pos)
-                    .enumerate()
+                    .par_iter()
+                    .map(to_sorting_key)
                     .collect()
             };
 
-            {
-                profile!("actual sorting");
-                tmp.par_sort_unstable_by_key(|pair| pair.1);
+            // The cached keys are from the last time we sorted.
+            if self.particles.sort_keys.len() == keys.len() {
+                profile!("compare keys");
+                let changed = keys
+                    .par_iter()
+                    .zip(&self.particles.sort_keys)
+                    .filter(|(key, cached)| key != cached)
+                    .count();
+                if changed * RESORT_FRACTION <= keys.len() {
+                    break 'simulated;
+                }
             }
 
-            let permutation: Vec<usize> = {
-                profile!("unzip");
-                tmp.into_iter().map(|(idx, _)| idx).collect()
+            let swaps = {
+                profile!("actual sorting");
+                // last step's order is mostly kept, a stable merge sort is fast for that
+                let mut permutation: Vec<usize> = (0..keys.len()).collect();
+                permutation.par_sort_by_key(|idx| keys[*idx]);
+                permutation_to_swaps(&permutation)
             };
 
             {
@@ -72,40 +79,45 @@ impl State {
                     velocities,
                     velocity_gradients,
                     collider_insides,
+                    velocity_fields,
 
                     // These will be overwritten anyway
                     reverse_sort_map: _,
+                    sort_keys: _,
+                    stencil_weights: _,
                     trial_position_gradients: _,
                     elastic_energies: _,
                     action_matrices: _,
                 } = &mut self.particles;
 
-                fn permute<'a, T: Clone + Send>(
+                fn permute<'a, T: Send>(
                     s: &Scope<'a>,
-                    permutation: &'a [usize],
-                    to_permute: &'a mut Vec<T>,
+                    swaps: &'a [(usize, usize)],
+                    to_permute: &'a mut [T],
                 ) {
                     s.spawn(move |_| {
-                        let lookup = to_permute.clone();
-                        assert!(permutation.len() == to_permute.len());
-                        for (&prior_position, to_permute) in permutation.iter().zip(to_permute) {
-                            *to_permute = lookup[prior_position].clone();
+                        for (a, b) in swaps {
+                            to_permute.swap(*a, *b);
                         }
                     });
                 }
 
                 scope(|s| {
-                    permute(s, &permutation, positions);
-                    permute(s, &permutation, sort_map);
-                    permute(s, &permutation, parameters);
-                    permute(s, &permutation, masses);
-                    permute(s, &permutation, initial_volumes);
-                    permute(s, &permutation, position_gradients);
-                    permute(s, &permutation, velocities);
-                    permute(s, &permutation, velocity_gradients);
-                    permute(s, &permutation, collider_insides);
+                    permute(s, &swaps, positions);
+                    permute(s, &swaps, sort_map);
+                    permute(s, &swaps, parameters);
+                    permute(s, &swaps, masses);
+                    permute(s, &swaps, initial_volumes);
+                    permute(s, &swaps, position_gradients);
+                    permute(s, &swaps, velocities);
+                    permute(s, &swaps, velocity_gradients);
+                    permute(s, &swaps, collider_insides);
+                    permute(s, &swaps, velocity_fields);
+                    permute(s, &swaps, &mut keys);
                 });
             }
+            self.particles.sort_keys = keys;
+            self.particles.stencil_weights.clear();
 
             {
                 profile!("reverse sort map");
@@ -123,8 +135,8 @@ impl State {
             for collider in &mut self.collider_objects {
                 collider
                     .surface_samples
-                    .par_sort_unstable_by_key(|surface_sample| {
-                        to_sorting_pos(
+                    .par_sort_by_cached_key(|surface_sample| {
+                        to_sorting_key(
                             &collider
                                 .kinematic
                                 .to_world_position(surface_sample.position),
@@ -135,3 +147,40 @@ impl State {
         Ok(self)
     }
 }
+
+// Interleave the bits of the node coordinates (z-order curve).
+// Since blocks are a power of two in size, the particles of a block stay contiguous.
+pub(super) fn morton_key(shift: Vector3<i32>) -> u64 {
+    // 21 bits per axis, offset to be non-negative
+    fn spread(x: i32) -> u64 {
+        let mut x = (x as i64 + (1 << 20)) as u64 & 0x1f_ffff;
+        x = (x | x << 32) & 0x001f_0000_0000_ffff;
+        x = (x | x << 16) & 0x001f_0000_ff00_00ff;
+        x = (x | x << 8) & 0x100f_00f0_0f00_f00f;
+        x = (x | x << 4) & 0x10c3_0c30_c30c_30c3;
+        x = (x | x << 2) & 0x1249_2492_4924_9249;
+        x
+    }
+    spread(shift.x) << 2 | spread(shift.y) << 1 | spread(shift.z)
+}
+
+// `permutation[new] = old` is decomposed into cycles, each one is applied with swaps.
+// This way the vectors can be permuted in place without cloning them.
+pub(super) fn permutation_to_swaps(permutation: &[usize]) -> Vec<(usize, usize)> {
+    let mut visited = vec![false; permutation.len()];
+    let mut swaps = Vec::new();
+    for start in 0..permutation.len() {
+        if visited[start] {
+            continue;
+        }
+        visited[start] = true;
+        let mut current = start;
+        while permutation[current] != start {
+            let next = permutation[current];
+            visited[next] = true;
+            swaps.push((current, next));
+            current = next;
+        }
+    }
+    swaps
+}
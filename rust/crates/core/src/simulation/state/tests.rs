@@ -0,0 +1,249 @@
+// SPDX-License-Identifier: MIT
+//
+// Copyright 2025  Algebraic UG (haftungsbeschränkt)
+//
+// Use of this source code is governed by an MIT-style
+// license that can be found in the LICENSE_MIT file or at
+// https://opensource.org/licenses/MIT.
+
+// With either 'f64' or 'mixed_precision' accumulated results have to agree with a reference in f64.
+// Plain single precision loses the light particles, it's only checked to be roughly right.
+
+use std::sync::Arc;
+
+use blended_mpm_api::{A, T};
+use nalgebra::{Matrix3, Vector3};
+
+use crate::{
+    api::{GlobalSettings, Setup},
+    math::random::{random_stream, random_vector},
+    simulation::{
+        fluid::Fluid,
+        particles::{ParticleParameters, Particles},
+    },
+    weights::{kernel_quadratic, position_to_shift_quadratic},
+};
+
+use super::{
+    ObjectIndex, Phase, PhaseInput, State,
+    sort::{morton_key, permutation_to_swaps},
+};
+
+const GRID_NODE_SIZE: T = 0.1;
+
+// relative to the reference
+#[cfg(any(feature = "f64", feature = "mixed_precision"))]
+const TOLERANCE: A = 1e-12;
+#[cfg(not(any(feature = "f64", feature = "mixed_precision")))]
+const TOLERANCE: A = 1e-3;
+
+// One heavy particle and many light ones at the same position.
+// In single precision the light ones vanish next to the heavy one.
+pub(crate) fn heavy_and_light_particles(position: Vector3<T>) -> Particles {
+    let mut particles = Particles::default();
+    let masses = std::iter::once(1e4).chain(std::iter::repeat_n(1e-4, 10_000));
+    for (idx, mass) in masses.enumerate() {
+        particles.sort_map.push(idx);
+        particles.reverse_sort_map.push(idx);
+        particles.parameters.push(ParticleParameters::Fluid {
+            exponent: 7,
+            bulk_modulus: 1e5,
+            viscosity: 0.,
+            drag: 0.,
+            stiffness_damping: 0.,
+        });
+        particles.masses.push(mass);
+        particles.initial_volumes.push(1e-3);
+        particles.positions.push(position);
+        particles.position_gradients.push(Matrix3::identity());
+        particles
+            .velocities
+            .push(Vector3::new(1., -2., 0.5) * (1 + idx % 3) as T);
+        particles.velocity_gradients.push(Matrix3::zeros());
+        particles.elastic_energies.push(0.);
+        particles.collider_insides.push(Default::default());
+        particles.velocity_fields.push(None);
+    }
+    particles
+}
+
+pub(crate) fn state(particles: Particles) -> State {
+    State {
+        time: 0.,
+        phase: Phase::Sort,
+        name_map: Default::default(),
+        particles,
+        solid_objects: Vec::new(),
+        fluid_objects: Vec::new(),
+        collider_objects: Vec::new(),
+        grid_collider_distances: Default::default(),
+        grid_momentum: Default::default(),
+        grid_collider_momentums: Vec::new(),
+        grid_object_momentums: Vec::new(),
+    }
+}
+
+fn phase_input() -> PhaseInput {
+    PhaseInput {
+        max_time_step: 1e-3,
+        time_step: 1e-3,
+        explicit: false,
+        debug_mode: false,
+        setup: Arc::new(Setup {
+            settings: GlobalSettings {
+                grid_node_size: GRID_NODE_SIZE,
+                particle_size: GRID_NODE_SIZE / 2.,
+                frames_per_second: 24,
+                gravity: Vector3::zeros(),
+                contact_friction_factor: 0.,
+                air_drag: 0.,
+                seed: 0,
+                deterministic: true,
+            },
+            objects: Vec::new(),
+        }),
+    }
+}
+
+fn assert_close(value: A, reference: A) {
+    assert!(
+        (value - reference).abs() <= TOLERANCE * reference.abs(),
+        "{value} != {reference}"
+    );
+}
+
+#[test]
+fn scattered_mass_matches_reference() {
+    // away from the origin, where the positions have little precision left
+    let position = Vector3::new(100.0123, -50.0456, 20.0789);
+    let state = state(heavy_and_light_particles(position));
+    let masses = state.particles.masses.clone();
+
+    let state = state
+        .sort(phase_input())
+        .and_then(|state| state.update_momentum_maps(phase_input()))
+        .and_then(|state| state.scatter_momentum::<false>(phase_input()))
+        .unwrap();
+
+    let normalized = position / GRID_NODE_SIZE;
+    let shift = position_to_shift_quadratic(&position, GRID_NODE_SIZE);
+    for i in 0..3 {
+        for j in 0..3 {
+            for k in 0..3 {
+                let grid_idx = shift + Vector3::new(i, j, k);
+                let weight = (grid_idx.map(|x| x as T) - normalized)
+                    .map(kernel_quadratic)
+                    .product();
+                let reference = masses
+                    .iter()
+                    .map(|mass| (weight * mass) as f64)
+                    .sum::<f64>();
+
+                let node = state.grid_momentum.map.get(&grid_idx).unwrap();
+                assert_close(state.grid_momentum.masses[node], reference as A);
+            }
+        }
+    }
+}
+
+#[test]
+fn kinetic_energy_matches_reference() {
+    let state = state(heavy_and_light_particles(Vector3::repeat(1.)));
+    let reference = state
+        .particles
+        .masses
+        .iter()
+        .zip(&state.particles.velocities)
+        .map(|(mass, velocity)| 0.5 * *mass as f64 * velocity.cast::<f64>().norm_squared())
+        .sum::<f64>();
+
+    assert_close(state.diagnostics(true).kinetic_energy, reference as A);
+}
+
+#[test]
+fn objects_on_the_free_grid_have_grid_masses() {
+    let mut state = state(heavy_and_light_particles(Vector3::repeat(1.)));
+    // the heavy particle and half of the light ones, and the other half
+    let count = state.particles.masses.len();
+    for (idx, particles) in [0..count / 2, count / 2..count].into_iter().enumerate() {
+        state.fluid_objects.push(Fluid {
+            particles: particles.collect(),
+            velocity_field: None,
+        });
+        state
+            .name_map
+            .insert(format!("Fluid {idx}"), ObjectIndex::Fluid(idx));
+    }
+    let references = state
+        .fluid_objects
+        .iter()
+        .map(|fluid| {
+            fluid
+                .particles
+                .iter()
+                .map(|idx| state.particles.masses[*idx] as f64)
+                .sum::<f64>()
+        })
+        .collect::<Vec<_>>();
+
+    let state = state
+        .sort(phase_input())
+        .and_then(|state| state.update_momentum_maps(phase_input()))
+        .and_then(|state| state.scatter_momentum::<false>(phase_input()))
+        .unwrap();
+
+    let diagnostics = state.diagnostics(true);
+    for (idx, reference) in references.iter().enumerate() {
+        assert_close(
+            diagnostics.grid_masses[&format!("Fluid {idx}")],
+            *reference as A,
+        );
+    }
+    assert_close(
+        diagnostics.grid_masses.values().sum::<A>(),
+        diagnostics.free_grid_mass,
+    );
+}
+
+#[test]
+fn swaps_reproduce_permutation() {
+    for (seed, len) in [(0, 0), (1, 1), (2, 2), (3, 1000)] {
+        let stream = random_stream(seed, "permutation");
+        let mut permutation: Vec<usize> = (0..len).collect();
+        permutation.sort_by_cached_key(|idx| random_vector(stream, *idx as u64).x.to_bits());
+
+        let mut permuted: Vec<usize> = (0..len).collect();
+        for (a, b) in permutation_to_swaps(&permutation) {
+            permuted.swap(a, b);
+        }
+        assert_eq!(permuted, permutation);
+    }
+}
+
+#[test]
+fn morton_key_is_monotone_per_axis() {
+    // the 21 bits per axis
+    let (min, max) = (-(1 << 20), (1 << 20) - 1);
+    let stream = random_stream(0, "morton");
+    let shift = |index: u64| {
+        random_vector(stream, index).map(|x| min + (x as f64 * max as f64 * 2.) as i32)
+    };
+
+    let mut pairs: Vec<_> = (0..1000)
+        .map(|i| (shift(2 * i), shift(2 * i + 1)))
+        .collect();
+    pairs.push((Vector3::repeat(min), Vector3::repeat(max)));
+    for (a, b) in pairs {
+        for axis in 0..3 {
+            let (mut lower, mut higher) = (a, a);
+            lower[axis] = a[axis].min(b[axis]);
+            higher[axis] = a[axis].max(b[axis]);
+            if lower[axis] < higher[axis] {
+                assert!(
+                    morton_key(lower) < morton_key(higher),
+                    "{lower:?} doesn't sort before {higher:?}"
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,129 @@
+// SPDX-License-Identifier: MIT
+//
+// Copyright 2025  Algebraic UG (haftungsbeschränkt)
+//
+// Use of this source code is governed by an MIT-style
+// license that can be found in the LICENSE_MIT file or at
+// https://opensource.org/licenses/MIT.
+
+use std::ops::Range;
+
+use blended_mpm_api::T;
+use fxhash::FxHashMap;
+use nalgebra::Vector3;
+use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
+
+use crate::{
+    simulation::{
+        grids::{BLOCK_COLORS, block_color, block_of},
+        interpolate::stencil_weights::stencil_weights,
+    },
+    weights::kernel_quadratic_unrolled,
+};
+
+use super::{State, find_worst_incompatibility, profile};
+
+// Where a particle transfers to at one node of its stencil.
+// The grid is indexed in the order of `grid_momentums`.
+#[derive(Clone, Copy)]
+pub(super) struct TransferTarget {
+    // relative to the first node of the stencil
+    pub offset: Vector3<i32>,
+    pub grid: usize,
+    pub node: usize,
+}
+
+impl State {
+    // The stencils are reused by all transfers between the sort and the advection.
+    pub(super) fn prepare_stencil_weights(&mut self, grid_node_size: T) {
+        if self.particles.stencil_weights.len() != self.particles.positions.len() {
+            profile!("stencil weights");
+            self.particles.stencil_weights =
+                stencil_weights(&self.particles.positions, grid_node_size);
+        }
+    }
+
+    // Visit all particles s.t. no two particles that are visited at the same time share a node.
+    // Particles are grouped by the block of their stencil's first node, blocks are colored.
+    // Within a block the particles are visited in order, so accumulation order is fixed.
+    // Requires `prepare_stencil_weights`.
+    pub(super) fn par_for_each_particle_colored(&self, visit: impl Fn(usize) + Sync) {
+        let colored_blocks = {
+            profile!("color blocks");
+            // sorting already makes the particles of a block contiguous,
+            // but don't rely on it for correctness
+            let mut blocks: FxHashMap<Vector3<i32>, Vec<Range<usize>>> = Default::default();
+            let mut current: Option<(Vector3<i32>, Range<usize>)> = None;
+            for (particle_idx, stencil) in self.particles.stencil_weights.iter().enumerate() {
+                let block = block_of(stencil.shift);
+                match &mut current {
+                    Some((current_block, range)) if *current_block == block => {
+                        range.end = particle_idx + 1;
+                    }
+                    _ => {
+                        if let Some((block, range)) = current.take() {
+                            blocks.entry(block).or_default().push(range);
+                        }
+                        current = Some((block, particle_idx..particle_idx + 1));
+                    }
+                }
+            }
+            if let Some((block, range)) = current {
+                blocks.entry(block).or_default().push(range);
+            }
+
+            let mut colored_blocks: [Vec<Vec<Range<usize>>>; BLOCK_COLORS] = Default::default();
+            for (block, ranges) in blocks {
+                colored_blocks[block_color(block)].push(ranges);
+            }
+            colored_blocks
+        };
+
+        profile!("visit");
+        for blocks in colored_blocks {
+            blocks.par_iter().for_each(|ranges| {
+                ranges
+                    .iter()
+                    .flat_map(|range| range.clone())
+                    .for_each(&visit)
+            });
+        }
+    }
+
+    // Requires `prepare_stencil_weights`.
+    pub(super) fn transfer_targets(&self, particle_idx: usize) -> [TransferTarget; 27] {
+        let collider_inside = &self.particles.collider_insides[particle_idx];
+        let velocity_field = self.particles.velocity_fields[particle_idx];
+        let number_of_colliders = self.grid_collider_momentums.len();
+
+        let shift = self.particles.stencil_weights[particle_idx].shift;
+        let distances = self.grid_collider_distances.stencil(shift);
+        let free = self.grid_momentum.map.stencil(shift);
+        kernel_quadratic_unrolled!(|offset| {
+            let grid_idx = offset + shift;
+            let incompatibility = distances
+                .get(&grid_idx)
+                .and_then(|grid_node| find_worst_incompatibility(collider_inside, grid_node));
+
+            let (grid, node) = match (incompatibility, velocity_field) {
+                (Some(collider_idx), _) => (
+                    1 + collider_idx,
+                    self.grid_collider_momentums[collider_idx]
+                        .map
+                        .get(&grid_idx),
+                ),
+                (None, Some(field_idx)) => (
+                    1 + number_of_colliders + field_idx,
+                    self.grid_object_momentums[field_idx].map.get(&grid_idx),
+                ),
+                (None, None) => (0, free.get(&grid_idx)),
+            };
+
+            TransferTarget {
+                offset,
+                grid,
+                node: node.expect("missing node"),
+            }
+        })
+    }
+}
@@ -8,14 +8,17 @@
 
 use anyhow::Result;
 use nalgebra::Vector3;
-use rayon::iter::{
-    IndexedParallelIterator, IntoParallelRefIterator, ParallelBridge, ParallelIterator,
+use rayon::{
+    iter::{IndexedParallelIterator, IntoParallelRefIterator, ParallelBridge, ParallelIterator},
+    slice::ParallelSliceMut,
 };
-use std::{mem::take, sync::mpsc::channel, thread::spawn};
 
 use crate::{
-    simulation::state::find_worst_incompatibility,
-    weights::{kernel_quadratic_unrolled, position_to_shift_quadratic},
+    simulation::{
+        grids::{BLOCK_VOLUME, block_of},
+        state::find_worst_incompatibility,
+    },
+    weights::kernel_quadratic_unrolled,
 };
 
 use super::{PhaseInput, State, profile};
@@ -26,89 +29,66 @@ impl State {
     pub(super) fn update_momentum_maps(mut self, phase_input: PhaseInput) -> Result<Self> {
         profile!("update_momentum_maps");
         let grid_node_size = phase_input.setup.settings.grid_node_size;
+        self.prepare_stencil_weights(grid_node_size);
 
         {
             profile!("prune");
+            // blocks that didn't receive any mass in the last step
             self.grid_momentums_mut().par_bridge().for_each(|grid| {
-                grid.map
-                    .retain(|_, idx| !grid.contributors[*idx].get_mut().unwrap().is_empty());
+                let masses = &grid.masses;
+                grid.map.retain_blocks(|block_idx| {
+                    masses
+                        .get(block_idx * BLOCK_VOLUME..(block_idx + 1) * BLOCK_VOLUME)
+                        .is_some_and(|block| block.iter().any(|mass| *mass > 0.))
+                });
             });
         }
 
-        let collider_maps = {
-            profile!("lookup copies");
-            self.grid_collider_momentums
-                .iter_mut()
-                .map(|grid| grid.map.clone())
-                .collect::<Vec<_>>()
-        };
-
-        let (senders, collectors): (Vec<_>, Vec<_>) = self
-            .grid_collider_momentums
-            .iter_mut()
-            .map(|grid| {
-                let mut map = take(&mut grid.map);
-                let (tx, rx) = channel();
-                (
-                    tx,
-                    spawn(move || {
-                        while let Ok(grid_idx) = rx.recv() {
-                            map.insert(grid_idx, 0);
-                        }
-                        map
-                    }),
-                )
-            })
-            .unzip();
-
-        let new_common_entries = self
-            .particles
-            .positions
-            .par_iter()
-            .zip(&self.particles.collider_insides)
-            .flat_map_iter(|(position, collider_inside)| {
-                let shift = position_to_shift_quadratic(position, grid_node_size);
-                kernel_quadratic_unrolled!(|grid_idx| {
-                    let grid_idx = grid_idx + shift;
-                    let incompatibility =
-                        self.grid_collider_distances
-                            .get(&grid_idx)
-                            .and_then(|grid_node| {
-                                find_worst_incompatibility(collider_inside, &grid_node.lock())
-                            });
-
-                    if let Some(collider_idx) = incompatibility {
-                        if !collider_maps[collider_idx].contains_key(&grid_idx) {
-                            senders[collider_idx]
-                                .send(grid_idx)
-                                .expect("collector died");
-                        }
-                        return None;
-                    }
+        // Only the blocks are collected, sorting makes the insertion order deterministic.
+        let mut new_blocks = {
+            profile!("find new blocks");
+            let number_of_colliders = self.grid_collider_momentums.len();
+            self.particles
+                .stencil_weights
+                .par_iter()
+                .zip(&self.particles.collider_insides)
+                .zip(&self.particles.velocity_fields)
+                .flat_map_iter(|((stencil, collider_inside), velocity_field)| {
+                    let shift = stencil.shift;
+                    let distances = self.grid_collider_distances.stencil(shift);
+                    kernel_quadratic_unrolled!(|grid_idx| {
+                        let grid_idx = grid_idx + shift;
+                        let incompatibility = distances.get(&grid_idx).and_then(|grid_node| {
+                            find_worst_incompatibility(collider_inside, grid_node)
+                        });
 
-                    (!self.grid_momentum.map.contains_key(&grid_idx)).then_some(grid_idx)
+                        // same indexing as `grid_momentums`
+                        let (grid, map) = match (incompatibility, velocity_field) {
+                            (Some(collider_idx), _) => (
+                                1 + collider_idx,
+                                &self.grid_collider_momentums[collider_idx].map,
+                            ),
+                            (None, Some(field_idx)) => (
+                                1 + number_of_colliders + field_idx,
+                                &self.grid_object_momentums[*field_idx].map,
+                            ),
+                            (None, None) => (0, &self.grid_momentum.map),
+                        };
+                        (!map.contains_key(&grid_idx)).then(|| (grid, block_of(grid_idx)))
+                    })
+                    .into_iter()
+                    .flatten()
                 })
-                .into_iter()
-                .filter_map(|grid_idx| grid_idx)
-            })
-            .collect::<Vec<_>>();
-        self.grid_momentum
-            .map
-            .extend(new_common_entries.into_iter().map(|grid_idx| (grid_idx, 0)));
-
-        {
-            profile!("collect");
-            drop(senders);
-            self.grid_collider_momentums
-                .iter_mut()
-                .zip(collectors)
-                .for_each(|(grid, collector)| grid.map = collector.join().unwrap());
-        }
+                .collect::<Vec<_>>()
+        };
 
         {
-            profile!("re-index");
-            for grid in self.grid_momentums_mut() {
-                grid.map.values_mut().enumerate().for_each(|(i, e)| *e = i);
+            profile!("insert new blocks");
+            new_blocks.par_sort_unstable_by_key(|(grid, block)| (*grid, <[i32; 3]>::from(*block)));
+            new_blocks.dedup();
+            let mut grids = self.grid_momentums_mut().collect::<Vec<_>>();
+            for (grid, block) in new_blocks {
+                grids[grid].map.insert_block(block);
             }
         }
 
@@ -63,9 +63,19 @@ fn new(
 }
 
 #[pyfunction]
-fn load(uuid: String, cache_dir: String, max_bytes_on_disk: u64) -> Result<SimulationReference> {
+fn load(
+    uuid: String,
+    cache_dir: String,
+    max_bytes_on_disk: u64,
+    force_unlock: bool,
+) -> Result<SimulationReference> {
     try_with_context(|context| {
-        context.load_simulation(uuid.clone(), cache_dir.into(), max_bytes_on_disk)?;
+        context.load_simulation(
+            uuid.clone(),
+            cache_dir.into(),
+            max_bytes_on_disk,
+            force_unlock,
+        )?;
         Ok(SimulationReference(uuid))
     })
 }
@@ -94,6 +104,7 @@ impl SimulationReference {
         })
     }
 
+    #[allow(clippy::too_many_arguments)]
     fn start_compute(
         &self,
         time_step: f32,
@@ -102,6 +113,13 @@ impl SimulationReference {
         start_frame: usize,
         number_of_frames: usize,
         max_bytes_on_disk: u64,
+        max_bytes_in_memory: u64,
+        number_of_threads: usize,
+        low_priority: bool,
+        store_queue_depth: usize,
+        frame_codec: &str,
+        frame_policy: &str,
+        eviction_policy: &str,
     ) -> Result<()> {
         try_with_context(|context| {
             context.get_simulation_mut(&self.0)?.start_compute(
@@ -111,6 +129,13 @@ impl SimulationReference {
                 start_frame,
                 number_of_frames,
                 max_bytes_on_disk,
+                max_bytes_in_memory,
+                number_of_threads,
+                low_priority,
+                store_queue_depth,
+                from_str(frame_codec).context("Frame codec string isn't valid json")?,
+                from_str(frame_policy).context("Frame policy string isn't valid json")?,
+                from_str(eviction_policy).context("Eviction policy string isn't valid json")?,
             )
         })
     }
@@ -130,6 +155,10 @@ impl SimulationReference {
         })
     }
 
+    fn manifest(&self) -> Result<String> {
+        try_with_context(|context| Ok(to_string(&context.get_simulation(&self.0)?.manifest())?))
+    }
+
     fn available_attributes<'py>(
         &self,
         py: Python<'py>,